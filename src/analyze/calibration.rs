@@ -0,0 +1,319 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, fs::{read_to_string, write}, path::Path, str::FromStr, sync::{Arc, RwLock}};
+use super::{taxonomy, Analysis, AnalysisError, Attitude, Scores, Subject};
+
+/// How confidence values are calibrated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConfidenceMethod {
+    /// Confidences are the scores as they are.
+    #[default]
+    None,
+    /// The scores are sharpened or softened by one fitted temperature.
+    Temperature,
+    /// The top score is mapped to how often the top label was right, with isotonic regression.
+    Isotonic,
+}
+
+impl FromStr for ConfidenceMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "temperature" => Ok(Self::Temperature),
+            "isotonic" => Ok(Self::Isotonic),
+            _ => Err(format!("unknown confidence calibration \"{s}\", expected none, temperature or isotonic")),
+        }
+    }
+}
+
+impl fmt::Display for ConfidenceMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Temperature => "temperature",
+            Self::Isotonic => "isotonic",
+        })
+    }
+}
+
+/// A fitted mapping from scores to confidence values.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "kebab-case")]
+pub enum Confidence {
+    #[default]
+    None,
+    /// The scores raised to `1 / temperature` and shared out again to add up to 1.
+    /// Temperatures above 1 soften overconfident scores.
+    Temperature { temperature: f64 },
+    /// The chance the top label is right, for top scores up to each score, from lowest to highest.
+    Isotonic { points: Vec<(f64, f64)> },
+}
+
+impl Confidence {
+    /// The calibrated confidence in the top label, given its score and the scores of every label.
+    pub fn calibrate<T>(&self, top_score: f64, scores: &[(T, f64)]) -> f64 {
+        match self {
+            Self::None => top_score,
+            Self::Temperature { temperature } => {
+                let total = scores.iter().map(|(_, score)| sharpen(*score, *temperature)).sum::<f64>();
+                if total > 0.0 { sharpen(top_score, *temperature) / total } else { top_score }
+            }
+            Self::Isotonic { points } => points
+                .iter()
+                .find(|(score, _)| top_score <= *score)
+                .or(points.last())
+                .map(|(_, probability)| *probability)
+                .unwrap_or(top_score),
+        }
+    }
+
+    /// Fit a confidence calibration to the scores of labeled texts and their gold labels.
+    fn fit<T: Copy + PartialEq>(method: ConfidenceMethod, examples: &[(&[(T, f64)], T)]) -> Self {
+        match method {
+            ConfidenceMethod::None => Self::None,
+            ConfidenceMethod::Temperature => Self::Temperature { temperature: fit_temperature(examples) },
+            ConfidenceMethod::Isotonic => Self::Isotonic {
+                points: fit_isotonic(examples.iter().filter_map(|(scores, gold)| top(scores).map(|(label, score)| (score, label == *gold))).collect()),
+            },
+        }
+    }
+}
+
+fn sharpen(score: f64, temperature: f64) -> f64 {
+    score.max(1e-9).powf(1.0 / temperature)
+}
+
+/// The temperature that makes the gold labels most likely, searched on a log scale from 0.05 to 20.
+fn fit_temperature<T: Copy + PartialEq>(examples: &[(&[(T, f64)], T)]) -> f64 {
+    let negative_log_likelihood = |temperature: f64| {
+        examples
+            .iter()
+            .filter_map(|(scores, gold)| {
+                let gold_score = scores.iter().find(|(label, _)| label == gold)?.1;
+                let total = scores.iter().map(|(_, score)| sharpen(*score, temperature)).sum::<f64>();
+                Some(-(sharpen(gold_score, temperature) / total).ln())
+            })
+            .sum::<f64>()
+    };
+    (0..=200)
+        .map(|i| (0.05f64.ln() + (20.0f64.ln() - 0.05f64.ln()) * i as f64 / 200.0).exp())
+        .min_by(|a, b| negative_log_likelihood(*a).partial_cmp(&negative_log_likelihood(*b)).unwrap_or(std::cmp::Ordering::Equal))
+        .unwrap_or(1.0)
+}
+
+/// Pool adjacent violators: the non-decreasing step function closest to whether each top label was right.
+fn fit_isotonic(mut examples: Vec<(f64, bool)>) -> Vec<(f64, f64)> {
+    examples.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    // Each block is its highest score, the number of right answers, and the number of examples.
+    let mut blocks: Vec<(f64, f64, f64)> = vec![];
+    for (score, right) in examples {
+        // Equal scores must get the same confidence, so they start in the same block.
+        match blocks.last_mut() {
+            Some(last) if last.0 == score => *last = (score, last.1 + right as u8 as f64, last.2 + 1.0),
+            _ => blocks.push((score, right as u8 as f64, 1.0)),
+        }
+        while blocks.len() > 1 {
+            let (last, before) = (blocks[blocks.len() - 1], blocks[blocks.len() - 2]);
+            if before.1 / before.2 < last.1 / last.2 {
+                break;
+            }
+            blocks.pop();
+            *blocks.last_mut().unwrap() = (last.0, before.1 + last.1, before.2 + last.2);
+        }
+    }
+    blocks.into_iter().map(|(score, right, count)| (score, right / count)).collect()
+}
+
+/// The fitted threshold of a label, and how well it did on the labeled texts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LabelThreshold {
+    /// The lowest score the label needs for text to be given it.
+    pub threshold: f64,
+    /// The F1 score of the label with this threshold.
+    pub f1: f64,
+    /// How many labeled texts had the label.
+    pub support: usize,
+}
+
+/// The calibration of the attitudes or the subjects.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LabelCalibration {
+    /// The thresholds of the labels, by name. Labels without one use the taxonomy's threshold.
+    pub thresholds: BTreeMap<String, LabelThreshold>,
+    pub confidence: Confidence,
+}
+
+impl LabelCalibration {
+    /// Fit a threshold for each label, and the confidence calibration, to the scores of labeled texts.
+    pub fn fit<T: Copy + PartialEq + ToString>(examples: &[(&[(T, f64)], T)], method: ConfidenceMethod) -> Self {
+        let mut labels = vec![];
+        for (scores, gold) in examples {
+            for label in scores.iter().map(|(label, _)| *label).chain([*gold]) {
+                if !labels.contains(&label) {
+                    labels.push(label);
+                }
+            }
+        }
+        let tops = examples.iter().map(|(scores, gold)| (top(scores), *gold)).collect::<Vec<_>>();
+        let thresholds = labels
+            .into_iter()
+            .filter_map(|label| {
+                let support = tops.iter().filter(|(_, gold)| *gold == label).count();
+                let predicted = tops
+                    .iter()
+                    .filter_map(|(top, gold)| top.filter(|(top, _)| *top == label).map(|(_, score)| (score, *gold == label)))
+                    .collect::<Vec<_>>();
+                if predicted.is_empty() {
+                    return None;
+                }
+                // Text is given the label when its score is above the threshold, so each score is a candidate
+                // threshold that leaves out that score and the ones below it.
+                let f1 = |threshold: f64| {
+                    let true_positives = predicted.iter().filter(|(score, right)| *score > threshold && *right).count();
+                    let false_positives = predicted.iter().filter(|(score, right)| *score > threshold && !*right).count();
+                    let false_negatives = support - true_positives;
+                    if true_positives == 0 { 0.0 } else { 2.0 * true_positives as f64 / (2 * true_positives + false_positives + false_negatives) as f64 }
+                };
+                let (threshold, f1) = std::iter::once(0.0)
+                    .chain(predicted.iter().map(|(score, _)| *score))
+                    .map(|threshold| (threshold, f1(threshold)))
+                    .fold((0.0, -1.0), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+                Some((label.to_string(), LabelThreshold { threshold, f1, support }))
+            })
+            .collect();
+        Self { thresholds, confidence: Confidence::fit(method, examples) }
+    }
+
+    /// The label text is given, and the confidence in it: the top label if it scores above its threshold,
+    /// otherwise the fallback, with the confidence that the top label is wrong.
+    fn decide<T: Copy + ToString>(&self, scores: &[(T, f64)], fallback: T, default_threshold: f64) -> (T, f64) {
+        match top(scores) {
+            Some((label, score)) => {
+                let threshold = self.thresholds.get(&label.to_string()).map(|fit| fit.threshold).unwrap_or(default_threshold);
+                let confidence = self.confidence.calibrate(score, scores);
+                if score > threshold { (label, confidence) } else { (fallback, 1.0 - confidence) }
+            }
+            None => (fallback, 1.0),
+        }
+    }
+}
+
+fn top<T: Copy>(scores: &[(T, f64)]) -> Option<(T, f64)> {
+    scores.iter().copied().max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+/// Per-label thresholds and confidence calibrations fitted to hand-labeled texts with the `calibrate` command.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Calibration {
+    pub attitudes: LabelCalibration,
+    pub subjects: LabelCalibration,
+}
+
+impl Calibration {
+    /// Fit a calibration to the scores of labeled texts. Texts without a gold attitude or subject
+    /// are left out of fitting that one.
+    pub fn fit(examples: &[(Scores, Option<Attitude>, Option<Subject>)], method: ConfidenceMethod) -> Self {
+        let attitudes = examples
+            .iter()
+            .filter_map(|(scores, attitude, _)| Some((scores.attitudes.as_slice(), (*attitude)?)))
+            .collect::<Vec<_>>();
+        let subjects = examples
+            .iter()
+            .filter_map(|(scores, _, subject)| Some((scores.subjects.as_slice(), (*subject)?)))
+            .collect::<Vec<_>>();
+        Self {
+            attitudes: LabelCalibration::fit(&attitudes, method),
+            subjects: LabelCalibration::fit(&subjects, method),
+        }
+    }
+
+    /// The analysis of scores with the calibrated thresholds and confidences.
+    pub fn analysis(&self, scores: &Scores) -> Analysis {
+        let taxonomy = taxonomy();
+        let (attitude, attitude_confidence) = self.attitudes.decide(&scores.attitudes, Attitude::fallback(), taxonomy.attitudes.threshold);
+        let (subject, subject_confidence) = self.subjects.decide(&scores.subjects, Subject::fallback(), taxonomy.subjects.threshold);
+        Analysis { attitude, attitude_confidence, subject, subject_confidence, scores: Some(scores.clone()), ..Analysis::default() }
+    }
+
+    /// Read a calibration from a JSON file.
+    pub fn from_file(file: &Path) -> Result<Self, AnalysisError> {
+        let input_json = read_to_string(file)
+            .map_err(|err| AnalysisError::LabelError(format!("could not read {}: {err}", file.display())))?;
+        serde_json::from_str(&input_json)
+            .map_err(|err| AnalysisError::LabelError(format!("could not parse {}: {err}", file.display())))
+    }
+
+    /// Save the calibration as a JSON file.
+    pub fn save(&self, file: &Path) -> std::io::Result<()> {
+        write(file, serde_json::to_string_pretty(self)?)
+    }
+}
+
+lazy_static! {
+    static ref SHARED_CALIBRATION: RwLock<Option<Arc<Calibration>>> = RwLock::new(None);
+}
+
+/// The calibration applied to scores, if one was set.
+pub fn calibration() -> Option<Arc<Calibration>> {
+    SHARED_CALIBRATION.read().unwrap().clone()
+}
+
+/// Apply a calibration to scores from now on, instead of the taxonomy's thresholds.
+pub fn set_calibration(calibration: Calibration) {
+    *SHARED_CALIBRATION.write().unwrap() = Some(Arc::new(calibration));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isotonic_fit_pools_decreasing_neighbours() {
+        let points = fit_isotonic(vec![(0.4, true), (0.1, false), (0.3, false), (0.2, true), (0.4, true)]);
+        assert_eq!(points, [(0.1, 0.0), (0.3, 0.5), (0.4, 1.0)]);
+
+        let confidence = Confidence::Isotonic { points };
+        let scores = [("a", 0.25), ("b", 0.75)];
+        assert_eq!(confidence.calibrate(0.25, &scores), 0.5);
+        assert_eq!(confidence.calibrate(0.95, &scores), 1.0);
+    }
+
+    #[test]
+    fn temperature_softens_overconfident_scores() {
+        // The top label scores 0.9 but is right 3 times in 4, which a temperature of 2 matches: 3 / (3 + 1).
+        let scores: &[(&str, f64)] = &[("a", 0.9), ("b", 0.1)];
+        let examples = [(scores, "a"), (scores, "a"), (scores, "a"), (scores, "b")];
+        let temperature = fit_temperature(&examples);
+        assert!((temperature - 2.0).abs() < 0.05, "temperature {temperature}");
+        assert!((Confidence::Temperature { temperature }.calibrate(0.9, scores) - 0.75).abs() < 0.01);
+
+        // Always right: the scores are sharpened as far as the search goes.
+        let scores: &[(&str, f64)] = &[("a", 0.6), ("b", 0.4)];
+        assert!(fit_temperature(&[(scores, "a"), (scores, "a")]) < 0.06);
+    }
+
+    #[test]
+    fn thresholds_maximize_each_labels_f1() {
+        let examples: [(&[(&str, f64)], &str); 4] = [
+            (&[("a", 0.9), ("b", 0.1)], "a"),
+            (&[("a", 0.7), ("b", 0.3)], "a"),
+            (&[("a", 0.6), ("b", 0.4)], "b"),
+            (&[("a", 0.2), ("b", 0.8)], "b"),
+        ];
+        let calibration = LabelCalibration::fit(&examples, ConfidenceMethod::None);
+        // Scores above 0.6 leave out the one wrong "a" and keep both right ones.
+        let a = &calibration.thresholds["a"];
+        assert_eq!((a.threshold, a.f1, a.support), (0.6, 1.0, 2));
+        // "b" is only the top label once, and right, so any threshold below it does best: 2 / (2 + 0 + 1).
+        let b = &calibration.thresholds["b"];
+        assert_eq!((b.threshold, b.support), (0.0, 2));
+        assert!((b.f1 - 2.0 / 3.0).abs() < 1e-9);
+
+        assert_eq!(calibration.decide(&[("a", 0.65), ("b", 0.35)], "none", 0.3), ("a", 0.65));
+        let (label, confidence) = calibration.decide(&[("a", 0.55), ("b", 0.45)], "none", 0.3);
+        assert_eq!(label, "none");
+        assert!((confidence - 0.45).abs() < 1e-9);
+    }
+}
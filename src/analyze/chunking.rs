@@ -0,0 +1,177 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr, sync::RwLock};
+use super::Scores;
+
+/// How the scores of a long text's chunks are combined into the scores of the text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Aggregation {
+    /// The average score of each label over the chunks.
+    #[default]
+    Mean,
+    /// The highest score of each label in any chunk, so a label that fits one part of the text stands out.
+    Max,
+    /// The average score of each label, weighted by the number of words in each chunk.
+    LengthWeighted,
+}
+
+impl Aggregation {
+    /// Combine the scores of a text's chunks, given how many words each chunk has.
+    pub fn aggregate(&self, chunks: &[Scores], words: &[usize]) -> Scores {
+        let weights = match self {
+            Self::LengthWeighted => words.iter().map(|words| *words as f64).collect(),
+            _ => vec![1.0; chunks.len()],
+        };
+        Scores {
+            attitudes: self.combine(&chunks.iter().map(|scores| scores.attitudes.as_slice()).collect::<Vec<_>>(), &weights),
+            subjects: self.combine(&chunks.iter().map(|scores| scores.subjects.as_slice()).collect::<Vec<_>>(), &weights),
+        }
+    }
+
+    /// Combine the scores of each label of the first chunk with its scores in the other chunks.
+    fn combine<T: Copy + PartialEq>(&self, chunks: &[&[(T, f64)]], weights: &[f64]) -> Vec<(T, f64)> {
+        let Some(first) = chunks.first() else {
+            return vec![];
+        };
+        let total_weight = weights.iter().sum::<f64>();
+        first
+            .iter()
+            .map(|(label, _)| {
+                let scores = chunks
+                    .iter()
+                    .map(|chunk| chunk.iter().find(|(other, _)| other == label).map(|(_, score)| *score).unwrap_or(0.0));
+                let score = match self {
+                    Self::Max => scores.fold(0.0, f64::max),
+                    _ if total_weight > 0.0 => scores.zip(weights).map(|(score, weight)| score * weight).sum::<f64>() / total_weight,
+                    _ => 0.0,
+                };
+                (*label, score)
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Aggregation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mean" => Ok(Self::Mean),
+            "max" => Ok(Self::Max),
+            "length-weighted" => Ok(Self::LengthWeighted),
+            _ => Err(format!("unknown aggregation \"{s}\", expected mean, max or length-weighted")),
+        }
+    }
+}
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Mean => "mean",
+            Self::Max => "max",
+            Self::LengthWeighted => "length-weighted",
+        })
+    }
+}
+
+/// How texts too long for the classifier are split into overlapping windows of words.
+#[derive(Clone, Debug)]
+pub struct Chunking {
+    /// The most words in a chunk. Defaults to the most the classifier reads, if it has a limit.
+    pub window: Option<usize>,
+    /// How many words each chunk shares with the one before it, so sentences cut at a boundary are still read whole.
+    pub overlap: usize,
+    pub aggregation: Aggregation,
+}
+
+impl Default for Chunking {
+    fn default() -> Self {
+        Self { window: None, overlap: 32, aggregation: Aggregation::Mean }
+    }
+}
+
+impl Chunking {
+    /// Check that each chunk moves on past the one before it, which it can't if the overlap is the whole window.
+    pub fn check(&self) -> Result<(), String> {
+        match self.window {
+            Some(window) if self.overlap >= window => {
+                Err(format!("the chunk overlap of {} words must be less than the {window} words in a chunk", self.overlap))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Split a text into chunks of at most `window` words. Texts that fit are left as they are.
+    /// An overlap that doesn't fit in the window, which can happen when the window is the classifier's,
+    /// is cut down to half the window.
+    pub fn split(&self, text: &str, window: Option<usize>) -> Vec<String> {
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        let window = match window {
+            Some(window) if words.len() > window.max(1) => window.max(1),
+            _ => return vec![text.to_string()],
+        };
+        let overlap = if self.overlap < window { self.overlap } else { window / 2 };
+        let step = window - overlap;
+        let mut chunks = vec![];
+        let mut start = 0;
+        loop {
+            let end = (start + window).min(words.len());
+            chunks.push(words[start..end].join(" "));
+            if end == words.len() {
+                return chunks;
+            }
+            start += step;
+        }
+    }
+}
+
+/// A record that a text was classified in chunks.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Chunked {
+    /// How many chunks the text was split into.
+    pub chunks: usize,
+    /// How the chunk scores were combined.
+    pub aggregation: Aggregation,
+}
+
+lazy_static! {
+    static ref SHARED_CHUNKING: RwLock<Chunking> = RwLock::new(Chunking::default());
+}
+
+/// How long texts are split and their chunk scores combined.
+pub fn chunking() -> Chunking {
+    SHARED_CHUNKING.read().unwrap().clone()
+}
+
+/// Change how long texts are split and their chunk scores combined.
+pub fn set_chunking(chunking: Chunking) {
+    *SHARED_CHUNKING.write().unwrap() = chunking;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(words: usize) -> String {
+        (0..words).map(|i| i.to_string()).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn splits_into_overlapping_windows() {
+        let chunking = Chunking { window: None, overlap: 2, aggregation: Aggregation::Mean };
+        assert_eq!(chunking.split(&text(10), Some(4)), ["0 1 2 3", "2 3 4 5", "4 5 6 7", "6 7 8 9"]);
+        assert_eq!(chunking.split(&text(4), Some(4)), ["0 1 2 3"]);
+    }
+
+    #[test]
+    fn an_overlap_as_long_as_the_window_is_rejected_or_cut_down() {
+        let chunking = Chunking { window: Some(16), ..Default::default() };
+        assert!(chunking.check().is_err());
+        assert!(Chunking { overlap: 15, ..chunking.clone() }.check().is_ok());
+        assert!(Chunking::default().check().is_ok());
+
+        // With the classifier's window, the overlap is cut to half of it.
+        assert_eq!(chunking.split(&text(1000), Some(16)).len(), 124);
+    }
+}
@@ -0,0 +1,274 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, RwLock}};
+use super::{calibration, taxonomy, Analysis, AnalysisError, Attitude, Lexicon, Subject};
+
+/// How well a text matches attitude and subject labels, from 0 to 1.
+/// Labels a classifier did not score are left out.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Scores {
+    pub attitudes: Vec<(Attitude, f64)>,
+    pub subjects: Vec<(Subject, f64)>,
+}
+
+impl Scores {
+    /// The attitude with the highest score.
+    pub fn top_attitude(&self) -> Option<(Attitude, f64)> {
+        self.attitudes.iter().copied().max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// The subject with the highest score.
+    pub fn top_subject(&self) -> Option<(Subject, f64)> {
+        self.subjects.iter().copied().max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// The attitudes from the highest score to the lowest.
+    pub fn ranked_attitudes(&self) -> Vec<(Attitude, f64)> {
+        ranked(&self.attitudes)
+    }
+
+    /// The subjects from the highest score to the lowest.
+    pub fn ranked_subjects(&self) -> Vec<(Subject, f64)> {
+        ranked(&self.subjects)
+    }
+
+    /// How far the top attitude scores above the runner-up.
+    pub fn attitude_margin(&self) -> f64 {
+        margin(&self.ranked_attitudes())
+    }
+
+    /// How far the top subject scores above the runner-up.
+    pub fn subject_margin(&self) -> f64 {
+        margin(&self.ranked_subjects())
+    }
+
+    /// The scores shared out so they add up to 1, for scores of labels that were each judged on their own.
+    pub fn normalized(&self) -> Self {
+        Self { attitudes: normalized(&self.attitudes), subjects: normalized(&self.subjects) }
+    }
+
+    /// The labels that score above the shared taxonomy's multi-label thresholds.
+    pub fn multi_label(&self) -> Self {
+        let taxonomy = taxonomy();
+        self.multi_label_with(taxonomy.attitudes.multi_label_threshold, taxonomy.subjects.multi_label_threshold)
+    }
+
+    /// The labels that score above these thresholds, instead of the taxonomy's.
+    pub fn multi_label_with(&self, attitude_threshold: f64, subject_threshold: f64) -> Self {
+        Self {
+            attitudes: self.attitudes.iter().copied().filter(|(_, score)| *score > attitude_threshold).collect(),
+            subjects: self.subjects.iter().copied().filter(|(_, score)| *score > subject_threshold).collect(),
+        }
+    }
+
+    /// The top attitude and subject, if they score above the shared calibration's thresholds,
+    /// or the shared taxonomy's if no calibration was set.
+    /// Otherwise the text gets the fallback label, with the confidence that the top label is wrong.
+    pub fn analysis(&self) -> Analysis {
+        if let Some(calibration) = calibration() {
+            return calibration.analysis(self);
+        }
+        let taxonomy = taxonomy();
+        self.analysis_with(taxonomy.attitudes.threshold, taxonomy.subjects.threshold)
+    }
+
+    /// The top attitude and subject, if they score above these thresholds, instead of the taxonomy's.
+    pub fn analysis_with(&self, attitude_threshold: f64, subject_threshold: f64) -> Analysis {
+        let (attitude, attitude_confidence) = match self.top_attitude() {
+            Some((attitude, score)) if score > attitude_threshold => (attitude, score),
+            Some((_, score)) => (Attitude::fallback(), 1.0 - score),
+            None => (Attitude::fallback(), 1.0),
+        };
+        let (subject, subject_confidence) = match self.top_subject() {
+            Some((subject, score)) if score > subject_threshold => (subject, score),
+            Some((_, score)) => (Subject::fallback(), 1.0 - score),
+            None => (Subject::fallback(), 1.0),
+        };
+        Analysis { attitude, attitude_confidence, subject, subject_confidence, scores: Some(self.clone()), multi_label: None, chunked: None }
+    }
+}
+
+fn ranked<T: Copy>(scores: &[(T, f64)]) -> Vec<(T, f64)> {
+    let mut ranked = scores.to_vec();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked
+}
+
+fn normalized<T: Copy>(scores: &[(T, f64)]) -> Vec<(T, f64)> {
+    let total = scores.iter().map(|(_, score)| score).sum::<f64>();
+    scores.iter().map(|(label, score)| (*label, if total > 0.0 { score / total } else { 0.0 })).collect()
+}
+
+fn margin<T>(ranked: &[(T, f64)]) -> f64 {
+    match ranked {
+        [] => 0.0,
+        [(_, top)] => *top,
+        [(_, top), (_, runner_up), ..] => top - runner_up,
+    }
+}
+
+/// Something that scores how well a text matches each attitude and subject.
+pub trait Classifier: Send + Sync {
+    /// Scores that add up to at most 1, for picking the single label that fits best.
+    fn classify(&self, text: &str) -> Result<Scores, AnalysisError>;
+
+    /// The single-label scores, and the scores of each label judged on its own,
+    /// for texts that fit several labels. Defaults to the single-label scores for both.
+    fn classify_multilabel(&self, text: &str) -> Result<(Scores, Scores), AnalysisError> {
+        let scores = self.classify(text)?;
+        Ok((scores.clone(), scores))
+    }
+
+    /// Score several texts at once, which is much faster for models that batch their inputs.
+    /// Defaults to scoring the texts one at a time.
+    fn classify_batch(&self, texts: &[&str]) -> Result<Vec<Scores>, AnalysisError> {
+        texts.iter().map(|text| self.classify(text)).collect()
+    }
+
+    /// The multi-label scores of several texts at once. Defaults to scoring the texts one at a time.
+    fn classify_multilabel_batch(&self, texts: &[&str]) -> Result<Vec<(Scores, Scores)>, AnalysisError> {
+        texts.iter().map(|text| self.classify_multilabel(text)).collect()
+    }
+
+    /// The most words of a text the classifier reads. Longer texts are split into chunks.
+    fn max_words(&self) -> Option<usize> {
+        None
+    }
+
+    /// How the classifier's inference workers are keeping up, if it has any.
+    fn metrics(&self) -> Option<InferenceMetrics> {
+        None
+    }
+}
+
+/// How a pool of inference workers is keeping up.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InferenceMetrics {
+    /// How many model instances there are.
+    pub workers: usize,
+    /// How many texts are waiting for a worker.
+    pub queued: usize,
+    /// How many texts are being classified right now.
+    pub busy: usize,
+    /// How many texts have been classified.
+    pub completed: usize,
+    /// How many texts could not be classified.
+    pub failed: usize,
+    /// How long the workers have spent classifying, added up.
+    pub busy_secs: f64,
+    /// How long since the workers were started.
+    pub elapsed_secs: f64,
+}
+
+impl InferenceMetrics {
+    /// How many texts have been classified per second.
+    pub fn throughput(&self) -> f64 {
+        if self.elapsed_secs > 0.0 { self.completed as f64 / self.elapsed_secs } else { 0.0 }
+    }
+
+    /// The share of the time the workers have spent classifying, from 0 to 1.
+    /// Low utilization with texts queued means the workers are starved by something else.
+    pub fn utilization(&self) -> f64 {
+        let capacity = self.elapsed_secs * self.workers as f64;
+        if capacity > 0.0 { self.busy_secs / capacity } else { 0.0 }
+    }
+}
+
+/// The classifiers that can be chosen from the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// The zero-shot model. Needs the `zero-shot` feature, libtorch, and network access the first time.
+    ZeroShot,
+    /// The keyword lexicon, which needs no model files.
+    Lexicon,
+}
+
+impl Backend {
+    /// Create the classifier, for the labels of the shared taxonomy.
+    /// `models` is how many zero-shot model instances to run, which defaults from the CPU count and memory.
+    #[cfg_attr(not(feature = "zero-shot"), allow(unused_variables))]
+    pub fn classifier(&self, models: Option<usize>) -> Result<Arc<dyn Classifier>, AnalysisError> {
+        match self {
+            #[cfg(feature = "zero-shot")]
+            Self::ZeroShot => Ok(Arc::new(models.map(super::ZeroShot::new).unwrap_or_default())),
+            #[cfg(not(feature = "zero-shot"))]
+            Self::ZeroShot => Err(AnalysisError::ZeroShotError("built without the zero-shot feature".to_string())),
+            Self::Lexicon => Ok(Arc::new(Lexicon::from_taxonomy(&taxonomy())?)),
+        }
+    }
+}
+
+impl Default for Backend {
+    /// The zero-shot model if it was built in, otherwise the lexicon.
+    fn default() -> Self {
+        if cfg!(feature = "zero-shot") {
+            Self::ZeroShot
+        } else {
+            Self::Lexicon
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zero-shot" => Ok(Self::ZeroShot),
+            "lexicon" => Ok(Self::Lexicon),
+            _ => Err(format!("unknown classifier \"{s}\", expected zero-shot or lexicon")),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ZeroShot => "zero-shot",
+            Self::Lexicon => "lexicon",
+        })
+    }
+}
+
+static MULTI_LABEL: AtomicBool = AtomicBool::new(false);
+
+/// Whether to also give text every label that scores above the multi-label thresholds on its own.
+pub fn set_multi_label(multi_label: bool) {
+    MULTI_LABEL.store(multi_label, Ordering::SeqCst);
+}
+
+/// Is text given every label that scores above the multi-label thresholds?
+pub fn multi_label() -> bool {
+    MULTI_LABEL.load(Ordering::SeqCst)
+}
+
+/// How many texts are classified together by default.
+pub const DEFAULT_BATCH_SIZE: usize = 16;
+
+static BATCH_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_BATCH_SIZE);
+
+/// Classify up to this many texts together when analyzing submissions.
+pub fn set_batch_size(batch_size: usize) {
+    BATCH_SIZE.store(batch_size.max(1), Ordering::SeqCst);
+}
+
+/// How many texts are classified together when analyzing submissions.
+pub fn batch_size() -> usize {
+    BATCH_SIZE.load(Ordering::SeqCst)
+}
+
+lazy_static! {
+    static ref SHARED_CLASSIFIER: RwLock<Arc<dyn Classifier>> = RwLock::new(
+        Backend::default().classifier(None).expect("the default classifier is always built in")
+    );
+}
+
+/// The classifier used by `Analyze` and `AnalyzeSubmission`.
+pub fn classifier() -> Arc<dyn Classifier> {
+    SHARED_CLASSIFIER.read().unwrap().clone()
+}
+
+/// Replace the classifier used by `Analyze` and `AnalyzeSubmission`.
+pub fn set_classifier(classifier: Arc<dyn Classifier>) {
+    *SHARED_CLASSIFIER.write().unwrap() = classifier;
+}
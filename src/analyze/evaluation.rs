@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use super::{Analysis, GoldLabel};
+
+/// How well a label was predicted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LabelMetrics {
+    pub label: String,
+    /// The share of the texts given the label that had it.
+    pub precision: f64,
+    /// The share of the texts with the label that were given it.
+    pub recall: f64,
+    pub f1: f64,
+    /// How many texts had the label.
+    pub support: usize,
+    /// How many texts were given the label.
+    pub predicted: usize,
+}
+
+/// Precision, recall and F1 averaged over labels.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Averages {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+/// How often texts with each gold label were given each label.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConfusionMatrix {
+    pub labels: Vec<String>,
+    /// The number of texts by gold label, then by the label they were given, in the order of `labels`.
+    pub counts: Vec<Vec<usize>>,
+}
+
+/// A text that was given the wrong label.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Misclassified {
+    pub id: String,
+    pub text: String,
+    pub gold: String,
+    pub predicted: String,
+    /// How confident the classifier was in the wrong label.
+    pub confidence: f64,
+}
+
+/// How well the attitudes or the subjects were predicted.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LabelReport {
+    /// How many texts had a gold label.
+    pub examples: usize,
+    /// The share of texts given their gold label.
+    pub accuracy: f64,
+    pub labels: Vec<LabelMetrics>,
+    /// The average of the labels' metrics, so rare labels count as much as common ones.
+    pub macro_average: Averages,
+    /// The metrics of every prediction pooled together, so common labels count the most.
+    pub micro_average: Averages,
+    pub confusion: ConfusionMatrix,
+    /// The wrong labels the classifier was most confident in, most confident first.
+    pub worst: Vec<Misclassified>,
+}
+
+impl LabelReport {
+    /// Compare predicted labels with gold labels. Each example is its ID, text, gold label, predicted label,
+    /// and the confidence in the prediction. `worst` is how many misclassified examples to keep.
+    pub fn new(examples: &[(&str, &str, String, String, f64)], worst: usize) -> Self {
+        let mut labels = examples.iter().flat_map(|(_, _, gold, predicted, _)| [gold.clone(), predicted.clone()]).collect::<Vec<_>>();
+        labels.sort();
+        labels.dedup();
+
+        let index = |label: &String| labels.iter().position(|other| other == label).unwrap();
+        let mut counts = vec![vec![0; labels.len()]; labels.len()];
+        for (_, _, gold, predicted, _) in examples {
+            counts[index(gold)][index(predicted)] += 1;
+        }
+
+        let ratio = |a: usize, b: usize| if b == 0 { 0.0 } else { a as f64 / b as f64 };
+        let f1 = |precision: f64, recall: f64| if precision + recall == 0.0 { 0.0 } else { 2.0 * precision * recall / (precision + recall) };
+        let metrics = labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                let true_positives = counts[i][i];
+                let support = counts[i].iter().sum::<usize>();
+                let predicted = counts.iter().map(|row| row[i]).sum::<usize>();
+                let (precision, recall) = (ratio(true_positives, predicted), ratio(true_positives, support));
+                LabelMetrics { label: label.clone(), precision, recall, f1: f1(precision, recall), support, predicted }
+            })
+            .collect::<Vec<_>>();
+
+        // Labels that were predicted but never in the gold labels have no recall to average.
+        let gold_labels = metrics.iter().filter(|metrics| metrics.support > 0).collect::<Vec<_>>();
+        let mean = |value: fn(&LabelMetrics) -> f64| ratio(1, gold_labels.len()) * gold_labels.iter().map(|metrics| value(metrics)).sum::<f64>();
+        let macro_average = Averages {
+            precision: mean(|metrics| metrics.precision),
+            recall: mean(|metrics| metrics.recall),
+            f1: mean(|metrics| metrics.f1),
+        };
+        // With one label per text, every wrong label is both a false positive and a false negative,
+        // so micro precision, recall and F1 are all the accuracy.
+        let true_positives = (0..labels.len()).map(|i| counts[i][i]).sum::<usize>();
+        let accuracy = ratio(true_positives, examples.len());
+        let micro_average = Averages { precision: accuracy, recall: accuracy, f1: accuracy };
+
+        let mut misclassified = examples
+            .iter()
+            .filter(|(_, _, gold, predicted, _)| gold != predicted)
+            .map(|(id, text, gold, predicted, confidence)| Misclassified {
+                id: id.to_string(),
+                text: text.to_string(),
+                gold: gold.clone(),
+                predicted: predicted.clone(),
+                confidence: *confidence,
+            })
+            .collect::<Vec<_>>();
+        misclassified.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal));
+        misclassified.truncate(worst);
+
+        Self {
+            examples: examples.len(),
+            accuracy,
+            labels: metrics,
+            macro_average,
+            micro_average,
+            confusion: ConfusionMatrix { labels, counts },
+            worst: misclassified,
+        }
+    }
+}
+
+/// How well a classifier's attitudes and subjects match gold labels, made by the `evaluate` command.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Evaluation {
+    /// The classifier that was evaluated.
+    pub classifier: String,
+    /// The file of gold labels it was evaluated against.
+    pub gold: String,
+    pub attitudes: LabelReport,
+    pub subjects: LabelReport,
+}
+
+impl Evaluation {
+    /// Compare the analyses of labeled texts with their gold labels.
+    /// Texts without a gold attitude or subject are left out of evaluating that one.
+    pub fn new(classifier: &str, gold: &str, examples: &[(&GoldLabel, &str, &Analysis)], worst: usize) -> Self {
+        let attitudes = examples
+            .iter()
+            .filter_map(|(label, text, analysis)| {
+                Some((label.id.as_str(), *text, label.attitude?.to_string(), analysis.attitude.to_string(), analysis.attitude_confidence))
+            })
+            .collect::<Vec<_>>();
+        let subjects = examples
+            .iter()
+            .filter_map(|(label, text, analysis)| {
+                Some((label.id.as_str(), *text, label.subject?.to_string(), analysis.subject.to_string(), analysis.subject_confidence))
+            })
+            .collect::<Vec<_>>();
+        Self {
+            classifier: classifier.to_string(),
+            gold: gold.to_string(),
+            attitudes: LabelReport::new(&attitudes, worst),
+            subjects: LabelReport::new(&subjects, worst),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn reports_hand_computed_metrics() {
+        let examples = [("1", "a", "a", 0.8), ("2", "a", "a", 0.7), ("3", "a", "b", 0.9), ("4", "b", "b", 0.6), ("5", "b", "c", 0.6), ("6", "b", "b", 0.5)]
+            .map(|(id, gold, predicted, confidence)| (id, "text", gold.to_string(), predicted.to_string(), confidence));
+        let report = LabelReport::new(&examples, 1);
+
+        assert_eq!(report.confusion.labels, ["a", "b", "c"]);
+        assert_eq!(report.confusion.counts, [[2, 1, 0], [0, 2, 1], [0, 0, 0]]);
+        assert_eq!(report.examples, 6);
+        assert!(close(report.accuracy, 4.0 / 6.0));
+
+        // a: 2 of 2 predicted are right, 2 of 3 are found. b: 2 of 3 and 2 of 3. c is never right.
+        let [a, b, c] = &report.labels[..] else { panic!("expected three labels") };
+        assert!(close(a.precision, 1.0) && close(a.recall, 2.0 / 3.0) && close(a.f1, 0.8));
+        assert!(close(b.precision, 2.0 / 3.0) && close(b.recall, 2.0 / 3.0) && close(b.f1, 2.0 / 3.0));
+        assert_eq!((a.support, a.predicted, b.support, b.predicted, c.support, c.predicted), (3, 2, 3, 3, 0, 1));
+        assert_eq!(c.f1, 0.0);
+
+        // c was never a gold label, so only a and b are averaged.
+        assert!(close(report.macro_average.precision, 5.0 / 6.0));
+        assert!(close(report.macro_average.recall, 2.0 / 3.0));
+        assert!(close(report.macro_average.f1, (0.8 + 2.0 / 3.0) / 2.0));
+        assert!(close(report.micro_average.f1, 4.0 / 6.0));
+
+        assert_eq!(report.worst.len(), 1);
+        assert_eq!((report.worst[0].id.as_str(), report.worst[0].predicted.as_str()), ("3", "b"));
+    }
+}
@@ -0,0 +1,269 @@
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{collections::BTreeMap, fs::{read_to_string, OpenOptions}, io::Write, path::Path};
+use super::{AnalysisError, Attitude, Subject};
+
+/// The attitude and subject a person gave a post or comment, to check and tune the classifier against.
+///
+/// The labels saved by `generate --labels` are gold labels too.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GoldLabel {
+    /// The ID of the post or comment.
+    pub id: String,
+    /// The text that was labeled, if it is not looked up by ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// The attitude, if it was labeled.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "taxonomy_attitude")]
+    pub attitude: Option<Attitude>,
+    /// The subject, if it was labeled.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "taxonomy_subject")]
+    pub subject: Option<Subject>,
+    /// Who made the label, if it was recorded. Labels by different annotators are compared with `agreement`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotator: Option<String>,
+}
+
+/// The label of the shared taxonomy with this name. Gold labels must name one exactly, since any other name,
+/// like a misspelled one, would become a custom label that the classifier never predicts.
+fn known<T: ToString>(kind: &str, labels: Vec<T>, name: &str) -> Result<T, String> {
+    let names = labels.iter().map(ToString::to_string).collect::<Vec<_>>();
+    match names.iter().position(|label| label == name) {
+        Some(i) => Ok(labels.into_iter().nth(i).unwrap()),
+        None => Err(format!("unknown {kind} \"{name}\", expected one of {}", names.join(", "))),
+    }
+}
+
+fn taxonomy_attitude<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Attitude>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|name| known("attitude", Attitude::all(), &name).map_err(de::Error::custom))
+        .transpose()
+}
+
+fn taxonomy_subject<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Subject>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|name| known("subject", Subject::all(), &name).map_err(de::Error::custom))
+        .transpose()
+}
+
+/// Read gold labels from a JSON array, from JSON lines with one label per line,
+/// or from a CSV file with an `id` column and any of `text`, `attitude`, `subject` and `annotator`.
+pub fn read_gold(file: &Path) -> Result<Vec<GoldLabel>, AnalysisError> {
+    let input = read_to_string(file)
+        .map_err(|err| AnalysisError::LabelError(format!("could not read {}: {err}", file.display())))?;
+    if file.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv")) {
+        return read_csv(&input).map_err(|err| AnalysisError::LabelError(format!("could not parse {}: {err}", file.display())));
+    }
+    if input.trim_start().starts_with('[') {
+        return serde_json::from_str(&input)
+            .map_err(|err| AnalysisError::LabelError(format!("could not parse {}: {err}", file.display())));
+    }
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|err| AnalysisError::LabelError(format!("could not parse line {} of {}: {err}", i + 1, file.display())))
+        })
+        .collect()
+}
+
+/// The text of each gold label, given directly or looked up by ID in `texts`.
+/// Labels whose text is not found are left out.
+pub fn labeled_texts<'a>(gold: &'a [GoldLabel], texts: &BTreeMap<String, String>) -> Vec<(String, &'a GoldLabel)> {
+    gold.iter()
+        .filter_map(|label| Some((label.text.clone().or_else(|| texts.get(&label.id).cloned())?, label)))
+        .collect()
+}
+
+/// Append a label to a JSON lines file of gold labels, creating the file if it does not exist.
+pub fn append_gold(file: &Path, label: &GoldLabel) -> Result<(), AnalysisError> {
+    let existing = read_to_string(file).unwrap_or_default();
+    let is_csv = file.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    if is_csv || existing.trim_start().starts_with('[') {
+        return Err(AnalysisError::LabelError(format!("labels can only be appended to JSON lines, and {} is not", file.display())));
+    }
+    let line = serde_json::to_string(label).map_err(|err| AnalysisError::LabelError(err.to_string()))?;
+    let separator = if existing.is_empty() || existing.ends_with('\n') { "" } else { "\n" };
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file)
+        .and_then(|mut output| writeln!(output, "{separator}{line}"))
+        .map_err(|err| AnalysisError::LabelError(format!("could not write {}: {err}", file.display())))
+}
+
+/// How often two annotators gave the same texts the same attitude or subject.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LabelAgreement {
+    /// How many texts both annotators labeled.
+    pub texts: usize,
+    /// The share of those texts they gave the same label.
+    pub observed: f64,
+    /// Cohen's kappa: how much more often they agreed than they would by chance, from 1 when they always agree
+    /// down to 0 when they agree no more than chance, or below.
+    pub kappa: f64,
+}
+
+impl LabelAgreement {
+    fn new<T: Ord + Copy>(pairs: &[(T, T)]) -> Self {
+        if pairs.is_empty() {
+            return Self::default();
+        }
+        let total = pairs.len() as f64;
+        let observed = pairs.iter().filter(|(a, b)| a == b).count() as f64 / total;
+        let mut shares = BTreeMap::<T, (f64, f64)>::new();
+        for (a, b) in pairs {
+            shares.entry(*a).or_default().0 += 1.0 / total;
+            shares.entry(*b).or_default().1 += 1.0 / total;
+        }
+        let chance = shares.values().map(|(a, b)| a * b).sum::<f64>();
+        let kappa = if chance >= 1.0 { 1.0 } else { (observed - chance) / (1.0 - chance) };
+        Self { texts: pairs.len(), observed, kappa }
+    }
+}
+
+/// How well two annotators agree on the texts they both labeled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Agreement {
+    pub annotators: (String, String),
+    pub attitudes: LabelAgreement,
+    pub subjects: LabelAgreement,
+}
+
+/// The agreement of every pair of annotators who labeled some of the same texts.
+/// When an annotator labeled a text more than once, their last label counts. Labels without an annotator
+/// or an ID are left out, since texts are matched by ID.
+pub fn agreement(gold: &[GoldLabel]) -> Vec<Agreement> {
+    let mut by_annotator = BTreeMap::<&str, BTreeMap<&str, &GoldLabel>>::new();
+    for label in gold.iter().filter(|label| !label.id.is_empty()) {
+        if let Some(annotator) = &label.annotator {
+            by_annotator.entry(annotator).or_default().insert(&label.id, label);
+        }
+    }
+    let annotators = by_annotator.iter().collect::<Vec<_>>();
+    let mut agreements = vec![];
+    for (i, (first, first_labels)) in annotators.iter().enumerate() {
+        for (second, second_labels) in &annotators[i + 1..] {
+            let shared = first_labels
+                .iter()
+                .filter_map(|(id, label)| Some((*label, *second_labels.get(id)?)))
+                .collect::<Vec<_>>();
+            if shared.is_empty() {
+                continue;
+            }
+            let attitudes = shared.iter().filter_map(|(a, b)| Some((a.attitude?, b.attitude?))).collect::<Vec<_>>();
+            let subjects = shared.iter().filter_map(|(a, b)| Some((a.subject?, b.subject?))).collect::<Vec<_>>();
+            agreements.push(Agreement {
+                annotators: (first.to_string(), second.to_string()),
+                attitudes: LabelAgreement::new(&attitudes),
+                subjects: LabelAgreement::new(&subjects),
+            });
+        }
+    }
+    agreements
+}
+
+fn read_csv(input: &str) -> Result<Vec<GoldLabel>, String> {
+    let mut rows = csv_rows(input).into_iter();
+    let (_, header) = rows.next().ok_or("the file is empty")?;
+    let column = |name: &str| header.iter().position(|column| column.trim().eq_ignore_ascii_case(name));
+    let id = column("id").ok_or("there is no id column")?;
+    let (text, attitude, subject, annotator) = (column("text"), column("attitude"), column("subject"), column("annotator"));
+    let field = |row: &[String], column: Option<usize>| {
+        column.and_then(|column| row.get(column)).map(|field| field.trim().to_string()).filter(|field| !field.is_empty())
+    };
+    rows.filter(|(_, row)| row.iter().any(|field| !field.trim().is_empty()))
+        .map(|(line, row)| {
+            let in_line = |err: String| format!("line {line}: {err}");
+            Ok(GoldLabel {
+                id: field(&row, Some(id)).unwrap_or_default(),
+                text: field(&row, text),
+                attitude: field(&row, attitude).map(|name| known("attitude", Attitude::all(), &name)).transpose().map_err(in_line)?,
+                subject: field(&row, subject).map(|name| known("subject", Subject::all(), &name)).transpose().map_err(in_line)?,
+                annotator: field(&row, annotator),
+            })
+        })
+        .collect()
+}
+
+/// Split CSV into rows of fields, with the line each row starts on. Quoted fields can hold commas, newlines and doubled quotes.
+fn csv_rows(input: &str) -> Vec<(usize, Vec<String>)> {
+    let mut rows = vec![];
+    let (mut row, mut field) = (vec![], String::new());
+    let (mut line, mut row_line) = (1, 1);
+    let mut quoted = false;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push((row_line, std::mem::take(&mut row)));
+                row_line = line;
+            }
+            ('\r', false) => {}
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push((row_line, row));
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_labels_from_csv() {
+        let gold = read_csv("id,text,attitude,subject\nabc,\"Great, thanks\",Praise,\nxyz,,,Food\n").unwrap();
+        assert_eq!(gold.len(), 2);
+        assert_eq!((gold[0].text.as_deref(), gold[0].attitude, gold[0].subject), (Some("Great, thanks"), Some(Attitude::Praise), None));
+        assert_eq!((gold[1].attitude, gold[1].subject), (None, Some(Subject::Food)));
+    }
+
+    #[test]
+    fn labels_not_in_the_taxonomy_are_rejected() {
+        let err = read_csv("id,text,attitude\nabc,\"One\nTwo\",Praise\nxyz,Three,praise\n").unwrap_err();
+        assert!(err.starts_with("line 4: unknown attitude \"praise\""), "{err}");
+
+        let err = serde_json::from_str::<GoldLabel>(r#"{"id": "abc", "subject": "Sport"}"#).unwrap_err();
+        assert!(err.to_string().contains("unknown subject \"Sport\""), "{err}");
+        assert!(serde_json::from_str::<GoldLabel>(r#"{"id": "abc", "subject": "Sports"}"#).is_ok());
+    }
+
+    #[test]
+    fn agreement_leaves_out_labels_without_an_id() {
+        let label = |id: &str, attitude, annotator: &str| GoldLabel {
+            id: id.to_string(),
+            text: None,
+            attitude: Some(attitude),
+            subject: None,
+            annotator: Some(annotator.to_string()),
+        };
+        let gold = [
+            label("a", Attitude::Praise, "ann"),
+            label("a", Attitude::Praise, "bob"),
+            label("b", Attitude::Mocking, "ann"),
+            label("b", Attitude::Praise, "bob"),
+            label("", Attitude::Neutral, "ann"),
+            label("", Attitude::Complaint, "bob"),
+        ];
+        let agreements = agreement(&gold);
+        assert_eq!(agreements.len(), 1);
+        assert_eq!(agreements[0].attitudes.texts, 2);
+        assert_eq!(agreements[0].attitudes.observed, 0.5);
+        assert_eq!(agreements[0].subjects.texts, 0);
+    }
+}
@@ -0,0 +1,169 @@
+use super::{AnalysisError, Attitude, Classifier, LabelSet, Scores, Subject, Taxonomy};
+
+/// Classifies text by counting keywords for each attitude and subject.
+///
+/// It is much less accurate than the zero-shot model, but it is deterministic,
+/// needs no model files, and is fast enough for tests and quick runs.
+#[derive(Clone, Debug)]
+pub struct Lexicon {
+    /// Words and phrases that suggest each attitude.
+    pub attitudes: Vec<(Attitude, Vec<String>)>,
+    /// Words and phrases that suggest each subject.
+    pub subjects: Vec<(Subject, Vec<String>)>,
+}
+
+impl Default for Lexicon {
+    fn default() -> Self {
+        let words = |words: &[&str]| words.iter().map(|word| word.to_string()).collect::<Vec<_>>();
+        Self {
+            attitudes: vec![
+                (Attitude::Inquisitive, words(&["?", "why", "how", "what", "does anyone", "can someone", "anyone know", "explain", "wondering", "question"])),
+                (Attitude::Praise, words(&["amazing", "love", "great", "awesome", "best", "beautiful", "wonderful", "thank you", "thanks", "well done", "made my day"])),
+                (Attitude::Condemnation, words(&["disgrace", "shameful", "disgusting", "no excuse", "evil", "unacceptable", "should be stopped", "outrageous", "corrupt"])),
+                (Attitude::Agreement, words(&["agree", "exactly", "true", "you're right", "you are right", "is right", "same", "absolutely", "like you said", "well said"])),
+                (Attitude::Complaint, words(&["tired of", "broken", "worse", "expensive", "always", "annoying", "frustrating", "why does", "keeps"])),
+                (Attitude::Mocking, words(&["lol", "lmao", "sure", "totally", "genius", "imagine", "wow", "/s", "clown"])),
+                (Attitude::Disagreement, words(&["disagree", "wrong", "not how", "nothing like", "no", "nope", "opposite", "incorrect", "not true"])),
+                (Attitude::Annoyed, words(&["ugh", "again", "stop", "sick of", "please stop", "enough", "seriously", "sigh"])),
+                (Attitude::Neutral, words(&["news", "link", "mentioned", "thread", "source", "update"])),
+            ],
+            subjects: vec![
+                (Subject::Politics, words(&["election", "vote", "senate", "president", "prime minister", "government", "tax", "policy", "democrat", "republican", "congress"])),
+                (Subject::Religion, words(&["church", "god", "pray", "prayer", "pope", "religion", "religious", "faith", "bible", "jesus"])),
+                (Subject::Science, words(&["science", "research", "study", "physics", "telescope", "climate", "vaccine", "experiment", "scientists", "quantum"])),
+                (Subject::Food, words(&["food", "pizza", "bread", "recipe", "ramen", "cook", "cooking", "spicy", "dinner", "sourdough", "delicious"])),
+                (Subject::Animals, words(&["cat", "dog", "puppy", "kitten", "animal", "animals", "zoo", "elephant", "foxes", "pet", "bird"])),
+                (Subject::Sports, words(&["football", "match", "playoffs", "coach", "team", "game", "marathon", "season", "score", "league"])),
+                (Subject::Music, words(&["music", "album", "concert", "guitar", "band", "song", "songs", "singer", "playlist"])),
+                (Subject::Movies, words(&["movie", "movies", "film", "trailer", "sequel", "director", "actor", "cinema", "ending"])),
+                (Subject::Joke, words(&["joke", "jokes", "walks into a bar", "walking into a bar", "knock knock", "pun", "punchline", "dad jokes"])),
+                (Subject::Technology, words(&["phone", "software", "computer", "update", "rust", "code", "app", "tech", "self-driving", "linux", "ai"])),
+                (Subject::Discussion, words(&["debate", "discussion", "opinion", "rules", "approach", "community", "thoughts", "discuss"])),
+                (Subject::Personal, words(&["job", "apartment", "family", "birthday", "my life", "myself", "my wife", "my husband", "my parents"])),
+            ],
+        }
+    }
+}
+
+impl Lexicon {
+    /// A lexicon for the labels of a taxonomy. Labels without keywords use the built-in keywords
+    /// if they are built-in labels, or else their phrase. Fails for labels of a taxonomy that hasn't been set.
+    pub fn from_taxonomy(taxonomy: &Taxonomy) -> Result<Self, AnalysisError> {
+        let builtin = Self::default();
+        Ok(Self {
+            attitudes: keywords(&taxonomy.attitudes, Attitude::named, &builtin.attitudes)?,
+            subjects: keywords(&taxonomy.subjects, Subject::named, &builtin.subjects)?,
+        })
+    }
+}
+
+fn keywords<T: Copy + PartialEq>(
+    labels: &LabelSet,
+    named: fn(&str) -> Result<T, AnalysisError>,
+    builtin: &[(T, Vec<String>)],
+) -> Result<Vec<(T, Vec<String>)>, AnalysisError> {
+    labels
+        .labels
+        .iter()
+        .map(|label| {
+            let value = named(&label.name)?;
+            let keywords = if !label.keywords.is_empty() {
+                label.keywords.iter().map(|keyword| keyword.to_lowercase()).collect()
+            } else if let Some((_, keywords)) = builtin.iter().find(|(builtin, _)| *builtin == value) {
+                keywords.clone()
+            } else {
+                vec![label.phrase().to_lowercase()]
+            };
+            Ok((value, keywords))
+        })
+        .collect()
+}
+
+/// Lowercase the text and split it into words, keeping question marks as words of their own.
+fn words(text: &str) -> Vec<String> {
+    let mut normalized = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '\'' || c == '-' || c == '/' {
+            normalized.push(c);
+        } else if c == '?' {
+            normalized.push_str(" ? ");
+        } else {
+            normalized.push(' ');
+        }
+    }
+    normalized.split_whitespace().map(str::to_string).collect()
+}
+
+/// How often each label's keywords occur in the text.
+fn count<T: Copy>(text: &[String], labels: &[(T, Vec<String>)]) -> Vec<(T, usize)> {
+    let occurrences = |keyword: &str| {
+        let keyword = keyword.split_whitespace().collect::<Vec<_>>();
+        if keyword.is_empty() {
+            return 0;
+        }
+        text.windows(keyword.len()).filter(|window| window.iter().zip(&keyword).all(|(a, b)| a == b)).count()
+    };
+    labels
+        .iter()
+        .map(|(label, keywords)| (*label, keywords.iter().map(|keyword| occurrences(keyword)).sum::<usize>()))
+        .collect()
+}
+
+/// How often each label's keywords occur in the text, as a share of all the keywords that occur.
+/// One extra count of nothing in particular keeps a single match from being certain.
+fn score<T: Copy>(text: &[String], labels: &[(T, Vec<String>)]) -> Vec<(T, f64)> {
+    let counts = count(text, labels);
+    let total = counts.iter().map(|(_, count)| count).sum::<usize>() as f64 + 1.0;
+    counts.into_iter().map(|(label, count)| (label, count as f64 / total)).collect()
+}
+
+/// How often each label's keywords occur in the text, judged on its own: one match scores 0.5,
+/// two score 0.67, and so on.
+fn score_each<T: Copy>(text: &[String], labels: &[(T, Vec<String>)]) -> Vec<(T, f64)> {
+    count(text, labels).into_iter().map(|(label, count)| (label, count as f64 / (count as f64 + 1.0))).collect()
+}
+
+impl Classifier for Lexicon {
+    fn classify(&self, text: &str) -> Result<Scores, AnalysisError> {
+        let text = words(text);
+        Ok(Scores {
+            attitudes: score(&text, &self.attitudes),
+            subjects: score(&text, &self.subjects),
+        })
+    }
+
+    fn classify_multilabel(&self, text: &str) -> Result<(Scores, Scores), AnalysisError> {
+        let words = words(text);
+        let each = Scores {
+            attitudes: score_each(&words, &self.attitudes),
+            subjects: score_each(&words, &self.subjects),
+        };
+        Ok((self.classify(text)?, each))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn top(text: &str) -> (Option<Attitude>, Option<Subject>) {
+        let scores = Lexicon::default().classify(text).unwrap();
+        (scores.top_attitude().map(|(attitude, _)| attitude), scores.top_subject().map(|(subject, _)| subject))
+    }
+
+    #[test]
+    fn classifies_texts_by_their_keywords() {
+        assert_eq!(top("Does anyone know how the new telescope works?"), (Some(Attitude::Inquisitive), Some(Subject::Science)));
+        assert_eq!(top("I love this sourdough recipe, it is amazing."), (Some(Attitude::Praise), Some(Subject::Food)));
+        assert_eq!(top("Ugh, not the election again."), (Some(Attitude::Annoyed), Some(Subject::Politics)));
+        assert_eq!(top("You're right, I completely agree about the playoffs."), (Some(Attitude::Agreement), Some(Subject::Sports)));
+    }
+
+    #[test]
+    fn common_words_are_not_keywords() {
+        let scores = Lexicon::default().classify("I think this is what my friend told me, right?").unwrap();
+        let score = |attitude| scores.attitudes.iter().find(|(a, _)| *a == attitude).unwrap().1;
+        assert_eq!(score(Attitude::Agreement), 0.0);
+        assert!(scores.subjects.iter().all(|(_, score)| *score == 0.0));
+    }
+}
@@ -1,13 +1,31 @@
-use rust_bert::{
-    pipelines::{
-        zero_shot_classification::{ZeroShotClassificationModel, ZeroShotClassificationConfig},
-    }
-};
-use lazy_static::lazy_static;
-use serde::{Serialize, Deserialize};
-use std::{sync::Mutex, thread::sleep, time::Duration};
+use serde::{de::Error as _, Serialize, Deserialize, Serializer, Deserializer};
+use std::fmt;
+
+mod calibration;
+pub use calibration::*;
+
+mod chunking;
+pub use chunking::*;
+
+mod classifier;
+pub use classifier::*;
+
+mod evaluation;
+pub use evaluation::*;
 
-const MAX_LENGTH: usize = 192;
+mod gold;
+pub use gold::*;
+
+mod lexicon;
+pub use lexicon::*;
+
+mod taxonomy;
+pub use taxonomy::*;
+
+#[cfg(feature = "zero-shot")]
+mod zero_shot;
+#[cfg(feature = "zero-shot")]
+pub use zero_shot::*;
 
 #[derive(Clone, Debug)]
 pub enum AnalysisError {
@@ -16,7 +34,7 @@ pub enum AnalysisError {
     LabelError(String),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Attitude {
     Inquisitive,
     Praise,
@@ -26,11 +44,13 @@ pub enum Attitude {
     Mocking,
     Disagreement,
     Annoyed,
-    Neutral
+    Neutral,
+    /// A label from a custom taxonomy, by its place in the label registry.
+    Custom(u16),
 }
 
 impl Attitude {
-    const TRESHOLD: f64 = 0.3;
+    pub const TRESHOLD: f64 = 0.3;
 
     pub const VALUES: [Self; 9] = [
         Self::Neutral,
@@ -44,7 +64,7 @@ impl Attitude {
         Self::Annoyed,
     ];
 
-    const LABELS: [&'static str; 9] = [
+    pub const LABELS: [&'static str; 9] = [
         "question",
         "praise",
         "condemnation",
@@ -56,7 +76,7 @@ impl Attitude {
         "neutral"
     ];
 
-    fn from_label(label: &str) -> Result<Self, AnalysisError> {
+    pub fn from_label(label: &str) -> Result<Self, AnalysisError> {
         Ok(match label {
             "question" => Self::Inquisitive,
             "praise" => Self::Praise,
@@ -83,6 +103,7 @@ impl Attitude {
             Self::Annoyed => 0.0,
             Self::Neutral => 0.5,
             Self::Condemnation => 0.0,
+            Self::Custom(i) => ATTITUDES.weights(*i).0,
         }
     }
 
@@ -103,17 +124,64 @@ impl Attitude {
             Self::Annoyed => 0.2,
             Self::Neutral => 0.5,
             Self::Condemnation => 0.0,
+            Self::Custom(i) => ATTITUDES.weights(*i).1,
+        }
+    }
+
+    /// The built-in attitude with this name.
+    pub fn builtin(name: &str) -> Option<Self> {
+        Self::VALUES.into_iter().find(|attitude| format!("{attitude:?}") == name)
+    }
+
+    /// The attitude with this name: a built-in one, or else a custom one of the taxonomies set so far.
+    pub fn named(name: &str) -> Result<Self, AnalysisError> {
+        Self::builtin(name)
+            .or_else(|| ATTITUDES.find(name).map(Self::Custom))
+            .ok_or_else(|| AnalysisError::LabelError(format!("unknown attitude label {name}, not in the taxonomy")))
+    }
+
+    /// The attitudes of the shared taxonomy, starting with its fallback.
+    pub fn all() -> Vec<Self> {
+        let taxonomy = taxonomy();
+        let fallback = Self::registered(&taxonomy.attitudes.fallback);
+        let labels = taxonomy.attitudes.labels.iter().map(|label| Self::registered(&label.name)).filter(|attitude| *attitude != fallback);
+        std::iter::once(fallback).chain(labels).collect()
+    }
+
+    /// The attitude of text that doesn't match any label well enough, in the shared taxonomy.
+    pub fn fallback() -> Self {
+        Self::registered(&taxonomy().attitudes.fallback)
+    }
+
+    /// A label of the shared taxonomy, which `set_taxonomy` has registered.
+    fn registered(name: &str) -> Self {
+        Self::named(name).expect("the labels of the shared taxonomy are registered")
+    }
+}
+
+impl fmt::Display for Attitude {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Custom(i) => write!(f, "{}", ATTITUDES.name(*i)),
+            _ => write!(f, "{:?}", self),
         }
     }
 }
 
-impl ToString for Attitude {
-    fn to_string(&self) -> String {
-        format!("{:?}", self)
+/// Attitudes are saved by name, so custom labels can only be read back once their taxonomy is set.
+impl Serialize for Attitude {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Attitude {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::named(&String::deserialize(deserializer)?).map_err(|err| D::Error::custom(format!("{err:?}")))
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Subject {
     Politics,
     Religion,
@@ -127,11 +195,13 @@ pub enum Subject {
     Technology,
     Discussion,
     Personal,
-    Other
+    Other,
+    /// A label from a custom taxonomy, by its place in the label registry.
+    Custom(u16),
 }
 
 impl Subject {
-    const TRESHOLD: f64 = 0.3;
+    pub const TRESHOLD: f64 = 0.3;
 
     pub const VALUES: [Self; 13] = [
         Self::Other,
@@ -149,7 +219,7 @@ impl Subject {
         Self::Personal,
     ];
 
-    const LABELS: [&'static str; 12] = [
+    pub const LABELS: [&'static str; 12] = [
         "politics",
         "religion",
         "science",
@@ -164,7 +234,7 @@ impl Subject {
         "me",
     ];
 
-    fn from_label(label: &str) -> Result<Self, AnalysisError> {
+    pub fn from_label(label: &str) -> Result<Self, AnalysisError> {
         Ok(match label {
             "politics" => Self::Politics,
             "religion" => Self::Religion,
@@ -183,13 +253,61 @@ impl Subject {
     }
 }
 
-impl ToString for Subject {
-    fn to_string(&self) -> String {
-        format!("{:?}", self)
+impl Subject {
+    /// The built-in subject with this name.
+    pub fn builtin(name: &str) -> Option<Self> {
+        Self::VALUES.into_iter().find(|subject| format!("{subject:?}") == name)
+    }
+
+    /// The subject with this name: a built-in one, or else a custom one of the taxonomies set so far.
+    pub fn named(name: &str) -> Result<Self, AnalysisError> {
+        Self::builtin(name)
+            .or_else(|| SUBJECTS.find(name).map(Self::Custom))
+            .ok_or_else(|| AnalysisError::LabelError(format!("unknown subject label {name}, not in the taxonomy")))
+    }
+
+    /// The subjects of the shared taxonomy, starting with its fallback.
+    pub fn all() -> Vec<Self> {
+        let taxonomy = taxonomy();
+        let fallback = Self::registered(&taxonomy.subjects.fallback);
+        let labels = taxonomy.subjects.labels.iter().map(|label| Self::registered(&label.name)).filter(|subject| *subject != fallback);
+        std::iter::once(fallback).chain(labels).collect()
+    }
+
+    /// The subject of text that doesn't match any label well enough, in the shared taxonomy.
+    pub fn fallback() -> Self {
+        Self::registered(&taxonomy().subjects.fallback)
+    }
+
+    /// A label of the shared taxonomy, which `set_taxonomy` has registered.
+    fn registered(name: &str) -> Self {
+        Self::named(name).expect("the labels of the shared taxonomy are registered")
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Custom(i) => write!(f, "{}", SUBJECTS.name(*i)),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+/// Subjects are saved by name, so custom labels can only be read back once their taxonomy is set.
+impl Serialize for Subject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
     }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+impl<'de> Deserialize<'de> for Subject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::named(&String::deserialize(deserializer)?).map_err(|err| D::Error::custom(format!("{err:?}")))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Analysis {
     // How positive or negative is the text?
     // pub sentiment: bool,
@@ -201,211 +319,139 @@ pub struct Analysis {
     pub subject: Subject,
     /// How confident is the model in its subject analysis?
     pub subject_confidence: f64,
+    /// The score of every label, so runner-up labels and margins can be looked at,
+    /// and the text re-thresholded without classifying it again.
+    /// Missing for analyses saved before scores were kept, and for averages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scores: Option<Scores>,
+    /// Every label that scored above the multi-label thresholds on its own, when analyzed in multi-label mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multi_label: Option<Scores>,
+    /// How the text was split and its chunk scores combined, if it was too long to classify whole.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunked: Option<Chunked>,
 }
 
 impl Default for Analysis {
     fn default() -> Self {
         Self {
             // sentiment: false,
-            attitude: Attitude::Neutral,
+            attitude: Attitude::fallback(),
             attitude_confidence: 0.0,
-            subject: Subject::Other,
+            subject: Subject::fallback(),
             subject_confidence: 0.0,
+            scores: None,
+            multi_label: None,
+            chunked: None,
         }
     }
 }
 
-lazy_static! {
-    // static ref SENTIMENT_MODEL: Mutex<SentimentModel> = {
-    //     e// println!("loading sentiment model...");
-    //     let result = Mutex::new(SentimentModel::new(Default::default()).expect("failed to load sentiment model"));
-    //     e// println!("done loading sentiment model");
-    //     result
-    // };
-    static ref ZERO_SHOT_MODEL_0: Mutex<ZeroShotClassificationModel> = {
-        // println!("Loading model 0...");
-        // config.model_type = ModelType::Roberta;
-        let config: ZeroShotClassificationConfig = Default::default();
-        // config.model_type = ModelType::DistilBert;
-        // config.model_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertModelResources::DISTIL_BERT_SST2,
-        //     // gpt2::Gpt2ModelResources::DISTIL_GPT2,
-        //     // roberta::RobertaModelResources::ALL_DISTILROBERTA_V1,
-        // ));
-        // config.config_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertConfigResources::DISTIL_BERT_SST2,
-        //     // gpt2::Gpt2ConfigResources::DISTIL_GPT2,
-        //     // roberta::RobertaConfigResources::ALL_DISTILROBERTA_V1,
-        // ));
-        // config.vocab_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertVocabResources::DISTIL_BERT_SST2,
-        //     // gpt2::Gpt2VocabResources::DISTIL_GPT2,
-        //     // roberta::RobertaVocabResources::ALL_DISTILROBERTA_V1,
-        // ));
-        // config.merges_resource = Some(Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertVocabResources::DISTIL_BERT_SST2,
-        //     // gpt2::Gpt2MergesResources::DISTIL_GPT2,
-        //     // roberta::RobertaMergesResources::ALL_DISTILROBERTA_V1,
-        // )));
-        // config.merges_resource = None;
-        let result = Mutex::new(ZeroShotClassificationModel::new(config).expect("failed to load zero shot model"));
-        // println!("Loaded model 0");
-        result
-    };
-    static ref ZERO_SHOT_MODEL_1: Mutex<ZeroShotClassificationModel> = {
-        // println!("Loading model 1...");
-        let config: ZeroShotClassificationConfig = Default::default();
-        // config.model_type = ModelType::DistilBert;
-        // config.model_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertModelResources::DISTIL_BERT_SST2,
-        //     // albert::AlbertModelResources::ALBERT_BASE_V2,
-        //     // roberta::RobertaModelResources::DISTILROBERTA_BASE,
-        // ));
-        // config.config_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertConfigResources::DISTIL_BERT_SST2,
-        //     // albert::AlbertConfigResources::ALBERT_BASE_V2,
-        //     // roberta::RobertaConfigResources::DISTILROBERTA_BASE,
-        // ));
-        // config.vocab_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertVocabResources::DISTIL_BERT_SST2,
-        //     // albert::AlbertVocabResources::ALBERT_BASE_V2,
-        //     // roberta::RobertaVocabResources::DISTILROBERTA_BASE,
-        // ));
-        // config.merges_resource = None;
-        let result = Mutex::new(ZeroShotClassificationModel::new(config).expect("failed to load zero shot model"));
-        // println!("Loaded model 1");
-        result
-    };
-    static ref ZERO_SHOT_MODEL_2: Mutex<ZeroShotClassificationModel> = {
-        // println!("Loading model 2...");
-        let config: ZeroShotClassificationConfig = Default::default();
-        // config.model_type = ModelType::DistilBert;
-        // config.model_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertModelResources::DISTIL_BERT_SST2,
-        //     // albert::AlbertModelResources::ALBERT_BASE_V2,
-        //     // roberta::RobertaModelResources::DISTILROBERTA_BASE,
-        // ));
-        // config.config_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertConfigResources::DISTIL_BERT_SST2,
-        //     // albert::AlbertConfigResources::ALBERT_BASE_V2,
-        //     // roberta::RobertaConfigResources::DISTILROBERTA_BASE,
-        // ));
-        // config.vocab_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertVocabResources::DISTIL_BERT_SST2,
-        //     // albert::AlbertVocabResources::ALBERT_BASE_V2,
-        //     // roberta::RobertaVocabResources::DISTILROBERTA_BASE,
-        // ));
-        // config.merges_resource = None;
-        let result = Mutex::new(ZeroShotClassificationModel::new(config).expect("failed to load zero shot model"));
-        // println!("Loaded model 2");
-        result
-    };
-    static ref ZERO_SHOT_MODEL_3: Mutex<ZeroShotClassificationModel> = {
-        // println!("Loading model 3...");
-        let config: ZeroShotClassificationConfig = Default::default();
-        // config.model_type = ModelType::DistilBert;
-        // config.model_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertModelResources::DISTIL_BERT_SST2,
-        //     // albert::AlbertModelResources::ALBERT_BASE_V2,
-        //     // roberta::RobertaModelResources::DISTILROBERTA_BASE,
-        // ));
-        // config.config_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertConfigResources::DISTIL_BERT_SST2,
-        //     // albert::AlbertConfigResources::ALBERT_BASE_V2,
-        //     // roberta::RobertaConfigResources::DISTILROBERTA_BASE,
-        // ));
-        // config.vocab_resource = Box::new(RemoteResource::from_pretrained(
-        //     distilbert::DistilBertVocabResources::DISTIL_BERT_SST2,
-        //     // albert::AlbertVocabResources::ALBERT_BASE_V2,
-        //     // roberta::RobertaVocabResources::DISTILROBERTA_BASE,
-        // ));
-        // config.merges_resource = None;
-        let result = Mutex::new(ZeroShotClassificationModel::new(config).expect("failed to load zero shot model"));
-        // println!("Loaded model 3");
-        result
-    };
-    static ref ZERO_SHOT_MODEL_4: Mutex<ZeroShotClassificationModel> = {
-        // println!("Loading model 4...");
-        let config: ZeroShotClassificationConfig = Default::default();
-        let result = Mutex::new(ZeroShotClassificationModel::new(config).expect("failed to load zero shot model"));
-        // println!("Loaded model 4");
-        result
-    };
-    static ref ZERO_SHOT_MODEL_5: Mutex<ZeroShotClassificationModel> = {
-        // println!("Loading model 5...");
-        let config: ZeroShotClassificationConfig = Default::default();
-        let result = Mutex::new(ZeroShotClassificationModel::new(config).expect("failed to load zero shot model"));
-        // println!("Loaded model 5");
-        result
-    };
+impl Analysis {
+    /// The analysis the text would have gotten with other thresholds.
+    /// Analyses without scores are returned as they are.
+    pub fn rethreshold(&self, attitude_threshold: f64, subject_threshold: f64) -> Self {
+        match &self.scores {
+            Some(scores) => Self {
+                multi_label: self.multi_label.clone(),
+                chunked: self.chunked,
+                ..scores.analysis_with(attitude_threshold, subject_threshold)
+            },
+            None => self.clone(),
+        }
+    }
+
+    /// The attitudes of the text: every multi-label attitude, or else the single attitude.
+    pub fn attitudes(&self) -> Vec<Attitude> {
+        match &self.multi_label {
+            Some(labels) if !labels.attitudes.is_empty() => labels.attitudes.iter().map(|(attitude, _)| *attitude).collect(),
+            _ => vec![self.attitude],
+        }
+    }
+
+    /// The subjects of the text: every multi-label subject, or else the single subject.
+    pub fn subjects(&self) -> Vec<Subject> {
+        match &self.multi_label {
+            Some(labels) if !labels.subjects.is_empty() => labels.subjects.iter().map(|(subject, _)| *subject).collect(),
+            _ => vec![self.subject],
+        }
+    }
 }
 
 pub trait Analyze {
     fn analyze(&self) -> Result<Analysis, AnalysisError>;
 }
 
+/// Analyze text with the shared classifier.
 impl Analyze for &str {
     fn analyze(&self) -> Result<Analysis, AnalysisError> {
-        // println!("Analyzing: '{}'", self);
-        if self == &"" {
-            return Ok(Analysis::default());
-        }
-        let input = [*self];
-        let pool: &[&Mutex<ZeroShotClassificationModel>] = &[
-            &ZERO_SHOT_MODEL_0,
-            &ZERO_SHOT_MODEL_1,
-            &ZERO_SHOT_MODEL_2,
-            &ZERO_SHOT_MODEL_3,
-            // &ZERO_SHOT_MODEL_4,
-//            &ZERO_SHOT_MODEL_5
-        ];
-        // let sentiment_model = SENTIMENT_MODEL.lock().unwrap();
-        let mut i = 0;
-        loop {
-            if let Ok(ref mut classify_model) = pool[i].try_lock() {
-                // println!("Acquired {i}");
-                // let output = classify_model.predict(&input, &Subject::LABELS).unwrap();
-                // let classify_model = ZERO_SHOT_MODEL.lock().unwrap();
-        
-                // let output = sentiment_model.predict(&input);
-                let mut subjects = classify_model.predict(
-                    &input,
-                    &Subject::LABELS,
-                    // None,
-                    Some(Box::new(|label| format!("This text's subject is {}", label))),
-                    MAX_LENGTH,
-                );
-                subjects.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-        
-                let mut attitudes = classify_model.predict(
-                    &input,
-                    &Attitude::LABELS,
-                    Some(Box::new(|label| format!("This text's attitude is {}", label))),
-                    // None,
-                    MAX_LENGTH,
+        Ok(analyze_texts(&[self])?.remove(0))
+    }
+}
+
+/// Analyze a batch of texts with the shared classifier, giving the same analyses as analyzing them one at a time.
+/// If the batch cannot be classified, each text is tried on its own, so one bad text only fails itself.
+pub fn analyze_batch(texts: &[&str]) -> Vec<Result<Analysis, AnalysisError>> {
+    match analyze_texts(texts) {
+        Ok(analyses) => analyses.into_iter().map(Ok).collect(),
+        Err(_) => texts.iter().map(|text| text.analyze()).collect(),
+    }
+}
+
+/// Classify texts together. Texts longer than the chunking window are split into chunks,
+/// which are classified with the rest and combined back into the scores of their text.
+fn analyze_texts(texts: &[&str]) -> Result<Vec<Analysis>, AnalysisError> {
+    let classifier = classifier();
+    let chunking = chunking();
+    let window = chunking.window.or_else(|| classifier.max_words());
+    let chunks = texts
+        .iter()
+        .map(|text| if text.is_empty() { vec![] } else { chunking.split(text, window) })
+        .collect::<Vec<_>>();
+    let inputs = chunks.iter().flatten().map(String::as_str).collect::<Vec<_>>();
+    let scores = if inputs.is_empty() {
+        vec![]
+    } else if multi_label() {
+        classifier
+            .classify_multilabel_batch(&inputs)?
+            .into_iter()
+            .map(|(scores, independent)| (scores, Some(independent)))
+            .collect()
+    } else {
+        classifier.classify_batch(&inputs)?.into_iter().map(|scores| (scores, None)).collect::<Vec<_>>()
+    };
+    if scores.len() != inputs.len() {
+        return Err(AnalysisError::LabelError(format!("the classifier scored {} of {} texts", scores.len(), inputs.len())));
+    }
+
+    let mut scores = scores.into_iter();
+    Ok(chunks
+        .iter()
+        .map(|chunks| match chunks.len() {
+            0 => Analysis::default(),
+            1 => {
+                let (scores, independent) = scores.next().unwrap();
+                analysis(scores, independent)
+            }
+            n => {
+                let (chunk_scores, independent): (Vec<_>, Vec<_>) = scores.by_ref().take(n).unzip();
+                let words = chunks.iter().map(|chunk| chunk.split_whitespace().count()).collect::<Vec<_>>();
+                let aggregation = chunking.aggregation;
+                let independent = independent.into_iter().collect::<Option<Vec<_>>>();
+                let mut analysis = analysis(
+                    aggregation.aggregate(&chunk_scores, &words),
+                    independent.map(|independent| aggregation.aggregate(&independent, &words)),
                 );
-                attitudes.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-                // let sentiment = output[0].polarity == SentimentPolarity::Positive;
-                let (attitude, attitude_confidence) = (attitudes[0].score > Attitude::TRESHOLD)
-                    .then_some((Attitude::from_label(&attitudes[0].text)?, attitudes[0].score))
-                    .unwrap_or((Attitude::Neutral, 1.0 - attitudes[0].score));
-                let (subject, subject_confidence) = (subjects[0].score > Subject::TRESHOLD)
-                    .then_some((Subject::from_label(&subjects[0].text)?, subjects[0].score))
-                    .unwrap_or((Subject::Other, 1.0 - subjects[0].score));
-                let result = Analysis {
-                    // sentiment,
-                    attitude,
-                    attitude_confidence,
-                    subject,
-                    subject_confidence,
-                };
-                // println!("Result: '{}'\n -> {result:#?}", self);
-                return Ok(result)
+                analysis.chunked = Some(Chunked { chunks: n, aggregation });
+                analysis
             }
-            i += 1;
-            i = i % pool.len();
-            sleep(Duration::from_millis(100));
-        }
-    }
+        })
+        .collect())
 }
 
+/// The analysis of a text's scores, and the labels above the multi-label thresholds if it was scored for them.
+fn analysis(scores: Scores, independent: Option<Scores>) -> Analysis {
+    let mut analysis = scores.analysis();
+    analysis.multi_label = independent.map(|independent| independent.multi_label());
+    analysis
+}
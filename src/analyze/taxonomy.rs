@@ -0,0 +1,250 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{fs::read_to_string, path::Path, sync::{Arc, RwLock}};
+use super::{AnalysisError, Attitude, Subject};
+
+/// A label of a taxonomy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaxonomyLabel {
+    /// The name the label is saved and graphed as, such as "balance complaint".
+    pub name: String,
+    /// How the label is put in the zero-shot hypothesis. Defaults to the name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phrase: Option<String>,
+    /// How positive an attitude is, from 0 to 1. Not used for subjects.
+    #[serde(default = "neutral_weight")]
+    pub positivity: f64,
+    /// How much an attitude agrees, from 0 to 1. Not used for subjects.
+    #[serde(default = "neutral_weight")]
+    pub agreement: f64,
+    /// Words and phrases the lexicon classifier looks for.
+    /// Defaults to the built-in keywords of built-in labels, or else the phrase.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+}
+
+fn neutral_weight() -> f64 {
+    0.5
+}
+
+impl TaxonomyLabel {
+    /// How the label is put in the zero-shot hypothesis.
+    pub fn phrase(&self) -> &str {
+        self.phrase.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// The attitude or subject labels of a taxonomy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LabelSet {
+    /// The zero-shot hypothesis, with `{}` where a label's phrase goes.
+    pub hypothesis: String,
+    /// The lowest score a label needs for text to be given it.
+    pub threshold: f64,
+    /// The lowest score a label needs on its own for text to be given it in multi-label mode.
+    #[serde(default = "default_multi_label_threshold")]
+    pub multi_label_threshold: f64,
+    /// The label text is given when no label scores above the threshold.
+    pub fallback: String,
+    pub labels: Vec<TaxonomyLabel>,
+}
+
+fn default_multi_label_threshold() -> f64 {
+    0.5
+}
+
+impl LabelSet {
+    /// The label with this zero-shot phrase.
+    pub fn with_phrase(&self, phrase: &str) -> Option<&TaxonomyLabel> {
+        self.labels.iter().find(|label| label.phrase() == phrase)
+    }
+
+    /// The hypothesis for a label's phrase.
+    pub fn hypothesis(&self, phrase: &str) -> String {
+        self.hypothesis.replace("{}", phrase)
+    }
+}
+
+/// The attitudes and subjects text is classified into.
+///
+/// Taxonomies are read from JSON files, so communities can be studied with their own labels:
+///
+/// ```json
+/// {
+///     "attitudes": {
+///         "hypothesis": "This text's attitude is {}",
+///         "threshold": 0.3,
+///         "multi_label_threshold": 0.5,
+///         "fallback": "Neutral",
+///         "labels": [
+///             { "name": "balance complaint", "positivity": 0.1, "agreement": 0.3 },
+///             { "name": "Praise", "phrase": "praise" }
+///         ]
+///     },
+///     "subjects": {
+///         "hypothesis": "This text's subject is {}",
+///         "threshold": 0.3,
+///         "fallback": "Other",
+///         "labels": [{ "name": "patch notes" }]
+///     }
+/// }
+/// ```
+///
+/// Labels named like a built-in attitude or subject are that attitude or subject, and keep its weights.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Taxonomy {
+    pub attitudes: LabelSet,
+    pub subjects: LabelSet,
+}
+
+impl Default for Taxonomy {
+    /// The built-in attitudes and subjects.
+    fn default() -> Self {
+        let label = |name: String, phrase: &str, positivity, agreement| TaxonomyLabel {
+            name,
+            phrase: Some(phrase.to_string()),
+            positivity,
+            agreement,
+            keywords: vec![],
+        };
+        Self {
+            attitudes: LabelSet {
+                hypothesis: "This text's attitude is {}".to_string(),
+                threshold: Attitude::TRESHOLD,
+                multi_label_threshold: default_multi_label_threshold(),
+                fallback: Attitude::Neutral.to_string(),
+                labels: Attitude::LABELS
+                    .iter()
+                    .filter_map(|phrase| Some((Attitude::from_label(phrase).ok()?, phrase)))
+                    .map(|(attitude, phrase)| label(attitude.to_string(), phrase, attitude.positivity(), attitude.agreement()))
+                    .collect(),
+            },
+            subjects: LabelSet {
+                hypothesis: "This text's subject is {}".to_string(),
+                threshold: Subject::TRESHOLD,
+                multi_label_threshold: default_multi_label_threshold(),
+                fallback: Subject::Other.to_string(),
+                labels: Subject::LABELS
+                    .iter()
+                    .filter_map(|phrase| Some((Subject::from_label(phrase).ok()?, phrase)))
+                    .map(|(subject, phrase)| label(subject.to_string(), phrase, 0.5, 0.5))
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl Taxonomy {
+    /// Read a taxonomy from a JSON file.
+    pub fn from_file(file: &Path) -> Result<Self, AnalysisError> {
+        let input_json = read_to_string(file)
+            .map_err(|err| AnalysisError::LabelError(format!("could not read {}: {err}", file.display())))?;
+        serde_json::from_str(&input_json)
+            .map_err(|err| AnalysisError::LabelError(format!("could not parse {}: {err}", file.display())))
+    }
+}
+
+/// The name and weights of a custom label.
+struct CustomLabel {
+    name: String,
+    positivity: f64,
+    agreement: f64,
+}
+
+/// The custom labels seen so far, so attitudes and subjects can refer to them by number and stay `Copy`.
+/// Labels are never removed, so the numbers stay valid for the whole run.
+pub(crate) struct Registry {
+    labels: RwLock<Vec<CustomLabel>>,
+}
+
+impl Registry {
+    fn new() -> Self {
+        Self { labels: RwLock::new(vec![]) }
+    }
+
+    /// The number of the label with this name, adding it if it is new.
+    /// Weights given for a label replace its earlier ones; new labels without weights are neutral.
+    pub(crate) fn intern(&self, name: &str, weights: Option<(f64, f64)>) -> Result<u16, AnalysisError> {
+        let mut labels = self.labels.write().unwrap();
+        let i = match labels.iter().position(|label| label.name == name) {
+            Some(i) => i,
+            None => {
+                labels.push(CustomLabel { name: name.to_string(), positivity: 0.5, agreement: 0.5 });
+                labels.len() - 1
+            }
+        };
+        let Ok(number) = u16::try_from(i) else {
+            labels.pop();
+            return Err(AnalysisError::LabelError(format!("too many custom labels to add {name}")));
+        };
+        if let Some((positivity, agreement)) = weights {
+            labels[i].positivity = positivity;
+            labels[i].agreement = agreement;
+        }
+        Ok(number)
+    }
+
+    /// The number of the label with this name, if it has been added.
+    pub(crate) fn find(&self, name: &str) -> Option<u16> {
+        let labels = self.labels.read().unwrap();
+        labels.iter().position(|label| label.name == name).map(|i| i as u16)
+    }
+
+    pub(crate) fn name(&self, i: u16) -> String {
+        self.labels.read().unwrap()[i as usize].name.clone()
+    }
+
+    /// The positivity and agreement of a label.
+    pub(crate) fn weights(&self, i: u16) -> (f64, f64) {
+        let labels = self.labels.read().unwrap();
+        (labels[i as usize].positivity, labels[i as usize].agreement)
+    }
+}
+
+lazy_static! {
+    pub(crate) static ref ATTITUDES: Registry = Registry::new();
+    pub(crate) static ref SUBJECTS: Registry = Registry::new();
+    static ref SHARED_TAXONOMY: RwLock<Arc<Taxonomy>> = RwLock::new(Arc::new(Taxonomy::default()));
+}
+
+/// The taxonomy used by the classifiers, and by statistics that list every label.
+pub fn taxonomy() -> Arc<Taxonomy> {
+    SHARED_TAXONOMY.read().unwrap().clone()
+}
+
+/// Replace the taxonomy used by the classifiers, registering its custom labels and their weights.
+/// Set it before creating a classifier, since classifiers may prepare for its labels,
+/// and before reading analyses that use its labels, since labels outside it are rejected.
+pub fn set_taxonomy(taxonomy: Taxonomy) -> Result<(), AnalysisError> {
+    for label in &taxonomy.attitudes.labels {
+        if Attitude::builtin(&label.name).is_none() {
+            ATTITUDES.intern(&label.name, Some((label.positivity, label.agreement)))?;
+        }
+    }
+    if Attitude::builtin(&taxonomy.attitudes.fallback).is_none() {
+        ATTITUDES.intern(&taxonomy.attitudes.fallback, None)?;
+    }
+    for name in taxonomy.subjects.labels.iter().map(|label| &label.name).chain([&taxonomy.subjects.fallback]) {
+        if Subject::builtin(name).is_none() {
+            SUBJECTS.intern(name, None)?;
+        }
+    }
+    *SHARED_TAXONOMY.write().unwrap() = Arc::new(taxonomy);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_outside_the_taxonomy_are_rejected() {
+        assert_eq!(serde_json::from_str::<Attitude>("\"Praise\"").unwrap(), Attitude::Praise);
+        assert!(serde_json::from_str::<Attitude>("\"never registered\"").is_err());
+        assert!(serde_json::from_str::<Subject>("\"never registered\"").is_err());
+        assert!(ATTITUDES.find("never registered").is_none());
+
+        let i = SUBJECTS.intern("patch notes", None).unwrap();
+        assert_eq!(serde_json::from_str::<Subject>("\"patch notes\"").unwrap(), Subject::Custom(i));
+    }
+}
@@ -0,0 +1,287 @@
+use rust_bert::{
+    pipelines::{
+        sequence_classification::Label,
+        zero_shot_classification::{ZeroShotClassificationModel, ZeroShotClassificationConfig},
+    }
+};
+use std::{
+    fs::read_to_string,
+    sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, mpsc, Arc, Mutex, OnceLock},
+    thread,
+    time::Instant,
+};
+use super::{taxonomy, AnalysisError, Attitude, Classifier, InferenceMetrics, LabelSet, Scores, Subject};
+
+const MAX_LENGTH: usize = 192;
+
+/// About as many words as fit in `MAX_LENGTH` tokens, leaving room for the hypothesis.
+const MAX_WORDS: usize = 128;
+
+/// About how much memory a loaded model takes, in bytes.
+const MODEL_MEMORY: u64 = 2 * 1024 * 1024 * 1024;
+
+/// The scores of the labels of a label set, by name.
+type LabelScores = Vec<(String, f64)>;
+
+/// How rust-bert puts a label's phrase in the hypothesis.
+type Template = Option<Box<dyn Fn(&str) -> String>>;
+
+/// The single-label scores of each text of a batch, with the scores of each label on its own in multi-label mode.
+type Batch = Vec<(Scores, Option<Scores>)>;
+
+/// A batch of texts to classify together, and where to send their scores.
+struct Job {
+    texts: Vec<String>,
+    multi_label: bool,
+    reply: mpsc::Sender<Result<Batch, AnalysisError>>,
+}
+
+/// What the workers have been doing, for `InferenceMetrics`.
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    busy: AtomicUsize,
+    completed: AtomicUsize,
+    failed: AtomicUsize,
+    busy_micros: AtomicU64,
+}
+
+/// The job queue, and when its workers were started.
+struct Pool {
+    jobs: mpsc::Sender<Job>,
+    started: Instant,
+}
+
+/// Classifies text with BART zero-shot classification models.
+///
+/// Each model is loaded by a worker thread of its own the first time text is classified,
+/// which needs libtorch and network access. Batches of texts are queued, and each worker
+/// classifies the next batch as soon as it is done with the last one.
+pub struct ZeroShot {
+    models: usize,
+    pool: OnceLock<Pool>,
+    counters: Arc<Counters>,
+}
+
+impl ZeroShot {
+    /// A classifier with this many model instances.
+    pub fn new(models: usize) -> Self {
+        Self { models: models.max(1), pool: OnceLock::new(), counters: Arc::default() }
+    }
+
+    /// How many models to run: one for every two CPUs, since each model runs on several threads,
+    /// but no more than fit in the available memory, and at least one.
+    pub fn default_models() -> usize {
+        let cpus = thread::available_parallelism().map(|cpus| cpus.get()).unwrap_or(1);
+        let fit_in_memory = available_memory().map(|memory| (memory / MODEL_MEMORY) as usize).unwrap_or(usize::MAX);
+        (cpus / 2).min(fit_in_memory).max(1)
+    }
+
+    /// Start the workers, if they have not been started yet.
+    fn pool(&self) -> &Pool {
+        self.pool.get_or_init(|| {
+            let (jobs, queue) = mpsc::channel::<Job>();
+            let queue = Arc::new(Mutex::new(queue));
+            for i in 0..self.models {
+                let queue = queue.clone();
+                let counters = self.counters.clone();
+                thread::Builder::new()
+                    .name(format!("zero-shot-{i}"))
+                    .spawn(move || work(&queue, &counters))
+                    .expect("failed to start a zero-shot worker");
+            }
+            Pool { jobs, started: Instant::now() }
+        })
+    }
+
+    /// The single-label scores for each text, and the scores of each label on its own in multi-label mode.
+    fn classify_queued(&self, texts: &[&str], multi_label: bool) -> Result<Batch, AnalysisError> {
+        let (reply, scores) = mpsc::channel();
+        self.counters.queued.fetch_add(texts.len(), Ordering::SeqCst);
+        self.pool()
+            .jobs
+            .send(Job { texts: texts.iter().map(|text| text.to_string()).collect(), multi_label, reply })
+            .map_err(|_| AnalysisError::ZeroShotError("the zero-shot workers have stopped".to_string()))?;
+        scores
+            .recv()
+            .map_err(|_| AnalysisError::ZeroShotError("a zero-shot worker stopped before classifying the text".to_string()))?
+    }
+}
+
+impl Default for ZeroShot {
+    fn default() -> Self {
+        Self::new(Self::default_models())
+    }
+}
+
+impl Classifier for ZeroShot {
+    fn classify(&self, text: &str) -> Result<Scores, AnalysisError> {
+        Ok(self.classify_batch(&[text])?.remove(0))
+    }
+
+    fn classify_multilabel(&self, text: &str) -> Result<(Scores, Scores), AnalysisError> {
+        Ok(self.classify_multilabel_batch(&[text])?.remove(0))
+    }
+
+    fn classify_batch(&self, texts: &[&str]) -> Result<Vec<Scores>, AnalysisError> {
+        Ok(self.classify_queued(texts, false)?.into_iter().map(|(scores, _)| scores).collect())
+    }
+
+    fn classify_multilabel_batch(&self, texts: &[&str]) -> Result<Vec<(Scores, Scores)>, AnalysisError> {
+        self.classify_queued(texts, true)?
+            .into_iter()
+            .map(|(scores, each)| Ok((scores, each.ok_or_else(|| AnalysisError::ZeroShotError("missing multi-label scores".to_string()))?)))
+            .collect()
+    }
+
+    fn max_words(&self) -> Option<usize> {
+        Some(MAX_WORDS)
+    }
+
+    fn metrics(&self) -> Option<InferenceMetrics> {
+        let counters = &self.counters;
+        Some(InferenceMetrics {
+            workers: self.models,
+            queued: counters.queued.load(Ordering::SeqCst),
+            busy: counters.busy.load(Ordering::SeqCst),
+            completed: counters.completed.load(Ordering::SeqCst),
+            failed: counters.failed.load(Ordering::SeqCst),
+            busy_secs: counters.busy_micros.load(Ordering::SeqCst) as f64 / 1_000_000.0,
+            elapsed_secs: self.pool.get().map(|pool| pool.started.elapsed().as_secs_f64()).unwrap_or(0.0),
+        })
+    }
+}
+
+/// Load a model and classify queued batches with it until the queue is dropped.
+fn work(queue: &Mutex<mpsc::Receiver<Job>>, counters: &Counters) {
+    let model = ZeroShotClassificationModel::new(ZeroShotClassificationConfig::default())
+        .map_err(|err| AnalysisError::ZeroShotError(format!("failed to load zero shot model: {err}")));
+    if let Err(err) = &model {
+        log::error!("{}: {err:?}", thread::current().name().unwrap_or("zero-shot worker"));
+    }
+    loop {
+        // Only hold the lock while waiting for a job, so the other workers can take the next one.
+        let job = match queue.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let texts = job.texts.len();
+        counters.queued.fetch_sub(texts, Ordering::SeqCst);
+        counters.busy.fetch_add(texts, Ordering::SeqCst);
+        let started = Instant::now();
+        let result = match &model {
+            Ok(model) => classify(model, &job.texts.iter().map(String::as_str).collect::<Vec<_>>(), job.multi_label),
+            Err(err) => Err(err.clone()),
+        };
+        counters.busy_micros.fetch_add(started.elapsed().as_micros() as u64, Ordering::SeqCst);
+        counters.busy.fetch_sub(texts, Ordering::SeqCst);
+        match result {
+            Ok(_) => counters.completed.fetch_add(texts, Ordering::SeqCst),
+            Err(_) => counters.failed.fetch_add(texts, Ordering::SeqCst),
+        };
+        let _ = job.reply.send(result);
+    }
+}
+
+/// Score the labels of the shared taxonomy for each text as a single label, and each on its own in multi-label mode.
+/// The batch goes through the model once for the subjects and once for the attitudes.
+fn classify(model: &ZeroShotClassificationModel, texts: &[&str], multi_label: bool) -> Result<Batch, AnalysisError> {
+    let taxonomy = taxonomy();
+    let subjects = predict(model, texts, &taxonomy.subjects, multi_label)?;
+    let attitudes = predict(model, texts, &taxonomy.attitudes, multi_label)?;
+    let scores = |attitudes: LabelScores, subjects: LabelScores| -> Result<Scores, AnalysisError> {
+        Ok(Scores {
+            attitudes: attitudes.into_iter().map(|(name, score)| Ok((Attitude::named(&name)?, score))).collect::<Result<_, AnalysisError>>()?,
+            subjects: subjects.into_iter().map(|(name, score)| Ok((Subject::named(&name)?, score))).collect::<Result<_, AnalysisError>>()?,
+        })
+    };
+    attitudes
+        .into_iter()
+        .zip(subjects)
+        .map(|((attitudes, each_attitude), (subjects, each_subject))| {
+            let each = match (each_attitude, each_subject) {
+                (Some(each_attitude), Some(each_subject)) => Some(scores(each_attitude, each_subject)?),
+                _ => None,
+            };
+            Ok((scores(attitudes, subjects)?, each))
+        })
+        .collect()
+}
+
+/// The memory available for new processes, from `/proc/meminfo`, in bytes.
+fn available_memory() -> Option<u64> {
+    let meminfo = read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kilobytes = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Score each input against the labels of a label set in one pass through the model, returning the names of the labels:
+/// first as a single label, then each label on its own if asked for.
+fn predict(model: &ZeroShotClassificationModel, input: &[&str], labels: &LabelSet, multi_label: bool) -> Result<Vec<(LabelScores, Option<LabelScores>)>, AnalysisError> {
+    let phrases = labels.labels.iter().map(|label| label.phrase()).collect::<Vec<_>>();
+    let hypothesis = labels.hypothesis.clone();
+    let template: Template = Some(Box::new(move |phrase| hypothesis.replace("{}", phrase)));
+    let each = model
+        .predict_multilabel(input, &phrases, template, MAX_LENGTH)
+        .map_err(|err| AnalysisError::ZeroShotError(err.to_string()))?;
+    if each.len() != input.len() {
+        return Err(AnalysisError::ZeroShotError(format!("expected scores for {} texts", input.len())));
+    }
+    each.iter()
+        .map(|each| {
+            let each = each.iter().map(|label| Ok((label_name(labels, label)?, label.score))).collect::<Result<LabelScores, AnalysisError>>()?;
+            Ok((single_label(&each), multi_label.then_some(each)))
+        })
+        .collect()
+}
+
+fn label_name(labels: &LabelSet, label: &Label) -> Result<String, AnalysisError> {
+    match labels.with_phrase(&label.text) {
+        Some(taxonomy_label) => Ok(taxonomy_label.name.clone()),
+        None => Err(AnalysisError::LabelError(format!("unknown label: {}", label.text))),
+    }
+}
+
+/// The single-label scores, from one softmax across the labels.
+/// The model scores each label's entailment against its contradiction, so the log-odds of a label's own score
+/// are how much more it entails the label than contradicts it, and those are what the softmax is taken over.
+fn single_label(each: &[(String, f64)]) -> LabelScores {
+    let log_odds = each
+        .iter()
+        .map(|(_, score)| {
+            let score = score.clamp(f64::EPSILON, 1.0 - f64::EPSILON);
+            (score / (1.0 - score)).ln()
+        })
+        .collect::<Vec<_>>();
+    let highest = log_odds.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let exps = log_odds.iter().map(|log_odds| (log_odds - highest).exp()).collect::<Vec<_>>();
+    let total = exps.iter().sum::<f64>();
+    each.iter().zip(exps).map(|((name, _), exp)| (name.clone(), exp / total)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores(names: &[(&str, f64)]) -> Vec<(String, f64)> {
+        names.iter().map(|(name, score)| (name.to_string(), *score)).collect()
+    }
+
+    #[test]
+    fn single_label_scores_are_one_softmax_across_the_labels() {
+        let single = single_label(&scores(&[("praise", 0.9), ("mocking", 0.5), ("neutral", 0.1)]));
+        assert!((single.iter().map(|(_, score)| score).sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(single[0].1 > single[1].1 && single[1].1 > single[2].1);
+        // Odds of 9, 1 and 1/9.
+        assert!((single[0].1 - 81.0 / 91.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn certain_labels_do_not_overflow() {
+        let single = single_label(&scores(&[("praise", 1.0), ("mocking", 1.0), ("neutral", 0.0)]));
+        assert!((single[0].1 - 0.5).abs() < 1e-9);
+        assert!(single[2].1 < 1e-9);
+        assert!(single_label(&[]).is_empty());
+    }
+}
@@ -1,8 +1,9 @@
-use std::{path::Path, sync::Arc, thread::{self, sleep}, time::Duration};
+use std::{path::{Path, PathBuf}, sync::Arc, thread::{self, sleep}, time::{Duration, Instant}};
 use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
 // use std::path::Path;
 use reddit_analyzer::*;
 use clap::Parser;
+use log::LevelFilter;
 
 const MIN_POST_SIZE: usize = 3;
 
@@ -10,19 +11,164 @@ const MIN_POST_SIZE: usize = 3;
 #[command(author, version, about, long_about = None)]
 struct Args {
    /// Name of subreddit to analyze
-   subreddit: String,
+   #[arg(required_unless_present = "user", conflicts_with = "user")]
+   subreddit: Option<String>,
+   /// Analyze the history of the user with this name, collected with `collect --user`, instead of a subreddit
+   #[arg(long)]
+   user: Option<String>,
+   /// How to classify text: with the "zero-shot" model, or the offline "lexicon" of keywords
+   #[arg(long, default_value_t = Backend::default())]
+   classifier: Backend,
+   /// Classify into the labels of this JSON taxonomy file instead of the built-in attitudes and subjects
+   #[arg(long)]
+   taxonomy: Option<PathBuf>,
+   /// Also give text every label that scores above the taxonomy's multi-label thresholds on its own
+   #[arg(long)]
+   multi_label: bool,
+   /// How many zero-shot model instances to run. Defaults from the CPU count and available memory
+   #[arg(long)]
+   models: Option<usize>,
+   /// How many texts to classify together
+   #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+   batch_size: usize,
+   /// Split texts longer than this many words into overlapping chunks. Defaults to the most the classifier reads
+   #[arg(long)]
+   chunk_words: Option<usize>,
+   /// How many words each chunk shares with the one before it
+   #[arg(long, default_value_t = Chunking::default().overlap)]
+   chunk_overlap: usize,
+   /// How the scores of a long text's chunks are combined: "mean", "max" or "length-weighted"
+   #[arg(long, default_value_t = Aggregation::default())]
+   aggregation: Aggregation,
+   /// Also analyze this part of each post on its own, for `graph --post-content`: "title" or "body".
+   /// Each post with a body then takes one more classification. With "combined", posts are only analyzed as a whole
+   #[arg(long, default_value_t = PostContent::Combined)]
+   post_content: PostContent,
+   /// Apply the per-label thresholds and confidence calibration fitted by `calibrate` and saved to this file
+   #[arg(long)]
+   calibration: Option<PathBuf>,
+   /// Print progress as JSON lines on stdout instead of drawing progress bars, for cron jobs and CI
+   #[arg(long)]
+   json: bool,
+   /// Append a leveled log of the run to this file
+   #[arg(long)]
+   log_file: Option<PathBuf>,
+   /// The least severe messages written to the log file: error, warn, info, debug or trace
+   #[arg(long, default_value_t = LevelFilter::Info, requires = "log_file")]
+   log_level: LevelFilter,
+}
+
+fn analyze_user(user_name: &str) {
+    let mut user = User::default();
+    user.restore(Path::new(&format!("data/users/{user_name}.json")));
+
+    let total_size = user.size();
+    let started = Instant::now();
+    let bar = ProgressBar::with_draw_target(Some(total_size as u64), draw_target());
+    bar.set_style(ProgressStyle::with_template(
+        " [{elapsed_precise}] {prefix:<22} {bar:30.cyan/red} {pos:>3}/{len:<3} [{per_sec:8}] {msg} {spinner}",
+    ).unwrap());
+    bar.set_prefix(format!("Analyzing u/{user_name}"));
+    bar.set_message("Loading models...");
+    bar.enable_steady_tick(Duration::from_millis(100));
+    let bar_clone = bar.clone();
+    let handle = thread::spawn(move || {
+        loop {
+            let total_analyzed = *ANALYZED_COMMENTS.lock().unwrap();
+            if total_analyzed != 0 {
+                bar_clone.set_message("Analyzing submissions...");
+            }
+            if total_analyzed >= total_size {
+                break;
+            }
+            bar_clone.set_position(total_analyzed as u64);
+            sleep(Duration::from_millis(100));
+        }
+    });
+
+    let analysis = user.analyze_submission().unwrap();
+    *ANALYZED_COMMENTS.lock().unwrap() = total_size;
+    handle.join().unwrap();
+    bar.finish_and_clear();
+    analysis.save(Path::new(&format!("analysis/u_{user_name}_user_analysis.json")));
+    emit(Event::AnalysisDone {
+        subreddit: format!("u/{user_name}"),
+        posts: user.posts.len(),
+        submissions: total_size,
+        elapsed_ms: started.elapsed().as_millis(),
+    });
+    report_metrics(&format!("u/{user_name}"));
+    if json_events() {
+        return;
+    }
+
+    println!("u/{user_name} by subreddit:");
+    for (subreddit, profile) in user.profile(&analysis) {
+        let percent = |count: usize| count as f64 / profile.submissions as f64 * 100.0;
+        println!(
+            "  r/{subreddit:<24} {:>4} submissions, positivity {:.2}, agreement {:.2}, attitude {}, subject {}",
+            profile.submissions,
+            profile.positivity,
+            profile.agreement,
+            profile.top_attitude().map_or("-".to_string(), |(attitude, count)| format!("{} ({:.0}%)", attitude, percent(count))),
+            profile.top_subject().map_or("-".to_string(), |(subject, count)| format!("{} ({:.0}%)", subject, percent(count))),
+        );
+    }
 }
 
 fn main() {
     let args = Args::parse();
-    
-    let subreddit_name = args.subreddit;
+    if args.json {
+        enable_json_events();
+    }
+    if let Some(file) = &args.log_file {
+        if let Err(err) = init_log_file(file, args.log_level) {
+            eprintln!("Could not open log file {}: {err}", file.display());
+        }
+    }
+    if let Some(file) = &args.taxonomy {
+        if let Err(err) = Taxonomy::from_file(file).and_then(set_taxonomy) {
+            eprintln!("Could not use the taxonomy: {err:?}");
+            return;
+        }
+    }
+    if let Some(file) = &args.calibration {
+        match Calibration::from_file(file) {
+            Ok(calibration) => set_calibration(calibration),
+            Err(err) => {
+                eprintln!("Could not use the calibration: {err:?}");
+                return;
+            }
+        }
+    }
+    set_multi_label(args.multi_label);
+    set_batch_size(args.batch_size);
+    let chunking = Chunking { window: args.chunk_words, overlap: args.chunk_overlap, aggregation: args.aggregation };
+    if let Err(err) = chunking.check() {
+        eprintln!("Could not split texts into chunks: {err}");
+        return;
+    }
+    set_chunking(chunking);
+    set_post_content(args.post_content);
+    match args.classifier.classifier(args.models) {
+        Ok(classifier) => set_classifier(classifier),
+        Err(err) => {
+            eprintln!("Could not use the {} classifier: {err:?}", args.classifier);
+            return;
+        }
+    }
+    if let Some(user_name) = &args.user {
+        analyze_user(user_name);
+        return;
+    }
+
+    let subreddit_name = args.subreddit.expect("a subreddit is required without --user");
     let mut sub = Subreddit::default();
-    sub.restore(Path::new(&format!("data/{subreddit_name}.json")));
-    sub.posts = sub.posts.into_iter().filter(|post| post.size() >= MIN_POST_SIZE).collect();
+    sub.restore(Path::new(&format!("data/{}.json", canonical_name(&subreddit_name))));
+    sub.posts.retain(|post| post.size() >= MIN_POST_SIZE);
     let mut post_analyses = vec![];
 
-    let multi_bar = MultiProgress::new();
+    let multi_bar = MultiProgress::with_draw_target(draw_target());
     let post_bar = multi_bar.add(ProgressBar::new(100));
 
     post_bar.set_prefix("Analyzing posts...");
@@ -43,6 +189,8 @@ fn main() {
     post_bar.set_message("Posts read");
 
     post_bar.set_prefix(format!("Analyzing {} posts", sub.posts.len()));
+    emit(Event::SubredditStarted { subreddit: subreddit_name.clone() });
+    let started = Instant::now();
     let comment_bar_style = ProgressStyle::with_template(
         " [{elapsed_precise}] {prefix:<22} {bar:30.cyan/red} {pos:>3}/{len:<3} [{per_sec:8}] {msg} {spinner}",
     ).unwrap();
@@ -79,13 +227,23 @@ fn main() {
 
         comment_bar.set_length(post.size() as u64);
         
-        let analysis = post.analyze_submission().unwrap();
+        let post_started = Instant::now();
+        let mut analysis = post.analyze_submission().unwrap();
+        if !post.subreddit.is_empty() {
+            analysis.subreddit = Some(post.subreddit.clone());
+        }
         comment_bar.set_message("Saving analysis...");
         analysis.save(Path::new(&format!("analysis/{subreddit_name}_post_analysis_{i}.json")));
 
         comment_bar.set_message("Cleaning up...");
         
         post_analyses.push(analysis);
+        emit(Event::PostAnalyzed {
+            subreddit: subreddit_name.clone(),
+            index: i,
+            submissions: post_size,
+            elapsed_ms: post_started.elapsed().as_millis(),
+        });
         post_bar.inc(1);
         comment_bar.set_message("Joining threads...");
         handle.join().unwrap();
@@ -97,4 +255,35 @@ fn main() {
     post_bar.finish_and_clear();
 
     post_analyses.save(Path::new(&format!("analysis/{subreddit_name}_subreddit_analysis.json")));
+    emit(Event::AnalysisDone {
+        subreddit: subreddit_name.clone(),
+        posts: total_posts,
+        submissions: total_size,
+        elapsed_ms: started.elapsed().as_millis(),
+    });
+    report_metrics(&subreddit_name);
+}
+
+/// Report how the classifier's inference workers kept up, if it has any.
+fn report_metrics(subreddit: &str) {
+    let Some(metrics) = classifier().metrics() else {
+        return;
+    };
+    if !json_events() {
+        println!(
+            "Classified {} texts at {:.1} texts/s with {} models, {:.0}% busy",
+            metrics.completed,
+            metrics.throughput(),
+            metrics.workers,
+            metrics.utilization() * 100.0,
+        );
+    }
+    emit(Event::InferenceMetrics {
+        subreddit: subreddit.to_string(),
+        workers: metrics.workers,
+        completed: metrics.completed,
+        failed: metrics.failed,
+        texts_per_sec: metrics.throughput(),
+        utilization: metrics.utilization(),
+    });
 }
@@ -0,0 +1,224 @@
+use reddit_analyzer::*;
+use clap::Parser;
+use rayon::prelude::*;
+use std::{collections::BTreeSet, io::{stdin, stdout, BufRead, Write}, path::{Path, PathBuf}};
+
+/// The most characters of a context text shown, so long posts don't push the text being labeled off screen.
+const CONTEXT_CHARS: usize = 300;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+   /// Names of the collected subreddits to sample posts and comments from
+   #[arg(required_unless_present = "agreement")]
+   subreddits: Vec<String>,
+   /// Your name, saved with each label so the labels of different annotators can be compared
+   #[arg(long, required_unless_present = "agreement")]
+   annotator: Option<String>,
+   /// The JSON lines file of gold labels the labels are appended to. Texts you already labeled in it are skipped,
+   /// so a session can be stopped and resumed. The attitudes predicted to sample texts by are saved next to it,
+   /// with the extension .strata.json
+   #[arg(long, default_value = "gold.jsonl")]
+   labels: PathBuf,
+   /// How many texts to label in this session
+   #[arg(long, default_value_t = 50)]
+   count: usize,
+   /// Texts are sampled in an order drawn from this seed. Annotators with the same seed and data are shown
+   /// the same texts in the same order, so their agreement can be measured
+   #[arg(long, default_value_t = 0)]
+   seed: u64,
+   /// How many of the submissions a comment replies to are shown with it, nearest last
+   #[arg(long, default_value_t = 2)]
+   context: usize,
+   /// How to predict the labels the sample is stratified by: with the "zero-shot" model, or the offline "lexicon" of keywords
+   #[arg(long, default_value_t = Backend::default())]
+   classifier: Backend,
+   /// Label with the attitudes and subjects of this JSON taxonomy file instead of the built-in ones
+   #[arg(long)]
+   taxonomy: Option<PathBuf>,
+   /// How many zero-shot model instances to run. Defaults from the CPU count and available memory
+   #[arg(long)]
+   models: Option<usize>,
+   /// How many texts to classify together
+   #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+   batch_size: usize,
+   /// Only print how well the annotators in the labels file agree
+   #[arg(long)]
+   agreement: bool,
+}
+
+/// What was answered when asked for a label.
+enum Answer<T> {
+    Label(T),
+    /// Leave this label out, but keep the other.
+    Unlabeled,
+    /// Skip the text without labeling it.
+    Skip,
+    Quit,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if let Some(file) = &args.taxonomy {
+        Taxonomy::from_file(file).and_then(set_taxonomy).map_err(|err| format!("{err:?}"))?;
+    }
+    let gold = if args.labels.exists() { read_gold(&args.labels).map_err(|err| format!("{err:?}"))? } else { vec![] };
+    if args.agreement {
+        print_agreement(&gold);
+        return Ok(());
+    }
+    let annotator = args.annotator.clone().unwrap_or_default();
+    let done = gold
+        .iter()
+        .filter(|label| !label.id.is_empty() && label.annotator.as_deref() == Some(annotator.as_str()))
+        .map(|label| label.id.clone())
+        .collect::<BTreeSet<_>>();
+
+    let mut items = vec![];
+    for name in &args.subreddits {
+        let mut subreddit = Subreddit::default();
+        subreddit.restore(Path::new(&format!("data/{}.json", canonical_name(name))));
+        items.extend(subreddit.annotation_items().into_iter().map(|item| (name.clone(), item)));
+    }
+    if items.is_empty() {
+        return Err("no posts or comments to label, collect the subreddits first".into());
+    }
+
+    // The whole sample is stratified every session, so the order stays the same as texts are labeled.
+    // The predicted attitudes are saved, so only texts collected since the last session are classified.
+    let strata_file = AnnotationStrata::file(&args.labels);
+    let mut saved = AnnotationStrata::default();
+    saved.restore(&strata_file);
+    let classifier_name = args.classifier.to_string();
+    if !saved.is_for(args.seed, &args.subreddits, &classifier_name) {
+        saved = AnnotationStrata { seed: args.seed, subreddits: args.subreddits.clone(), classifier: classifier_name, ..Default::default() };
+    }
+    let unclassified = items.iter().filter(|(name, item)| saved.attitude(name, &item.id).is_none()).collect::<Vec<_>>();
+    if !unclassified.is_empty() {
+        set_classifier(args.classifier.classifier(args.models).map_err(|err| format!("{err:?}"))?);
+        set_batch_size(args.batch_size);
+        println!("Predicting the attitudes of {} texts to sample from with the {} classifier...", unclassified.len(), args.classifier);
+        let predicted = unclassified
+            .par_chunks(batch_size())
+            .flat_map_iter(|batch| {
+                let analyses = analyze_batch(&batch.iter().map(|(_, item)| item.text.as_str()).collect::<Vec<_>>());
+                analyses.into_iter().map(|analysis| analysis.map(|analysis| analysis.attitude).unwrap_or_else(|_| Attitude::fallback()))
+            })
+            .collect::<Vec<_>>();
+        for ((name, item), attitude) in unclassified.iter().zip(predicted) {
+            saved.attitudes.entry(name.clone()).or_default().insert(item.id.clone(), attitude);
+        }
+        saved.save(&strata_file);
+    }
+    let strata = items
+        .iter()
+        .map(|(name, item)| (name.clone(), saved.attitude(name, &item.id).unwrap_or_else(Attitude::fallback)))
+        .collect::<Vec<_>>();
+    let sample = stratified_order(&strata, args.seed)
+        .into_iter()
+        .filter(|i| !done.contains(&items[*i].1.id))
+        .take(args.count)
+        .collect::<Vec<_>>();
+    if sample.is_empty() {
+        println!("You have labeled every text in {}", args.subreddits.join(", "));
+        print_agreement(&gold);
+        return Ok(());
+    }
+    println!("{} texts labeled by {annotator} so far, {} to label in this session", done.len(), sample.len());
+    println!("Answer with a label's number or name, \"-\" to leave it unlabeled, \"s\" to skip the text or \"q\" to quit");
+
+    let (attitudes, subjects) = (Attitude::all(), Subject::all());
+    let mut input = stdin().lock().lines();
+    let mut labeled = 0;
+    for (n, i) in sample.iter().enumerate() {
+        let (name, item) = &items[*i];
+        println!();
+        println!("[{}/{}] r/{name} {}", n + 1, sample.len(), item.id);
+        for context in item.context.iter().skip(item.context.len().saturating_sub(args.context)) {
+            let context = context.replace('\n', " ");
+            let context = if context.chars().count() > CONTEXT_CHARS {
+                format!("{}...", context.chars().take(CONTEXT_CHARS - 3).collect::<String>())
+            } else {
+                context
+            };
+            println!("  > {context}");
+        }
+        for line in item.text.lines() {
+            println!("  {line}");
+        }
+
+        let attitude = match ask("Attitude", &attitudes, &mut input)? {
+            Answer::Label(attitude) => Some(attitude),
+            Answer::Unlabeled => None,
+            Answer::Skip => continue,
+            Answer::Quit => break,
+        };
+        let subject = match ask("Subject", &subjects, &mut input)? {
+            Answer::Label(subject) => Some(subject),
+            Answer::Unlabeled => None,
+            Answer::Skip => continue,
+            Answer::Quit => break,
+        };
+        if attitude.is_none() && subject.is_none() {
+            continue;
+        }
+        let label = GoldLabel { id: item.id.clone(), text: Some(item.text.clone()), attitude, subject, annotator: Some(annotator.clone()) };
+        append_gold(&args.labels, &label).map_err(|err| format!("{err:?}"))?;
+        labeled += 1;
+    }
+
+    println!();
+    println!("Labeled {labeled} texts, {} by {annotator} in {}", done.len() + labeled, args.labels.display());
+    print_agreement(&read_gold(&args.labels).unwrap_or(gold));
+    Ok(())
+}
+
+/// Ask for one of the labels until the answer is understood. The end of the input quits.
+fn ask<T: Copy + ToString>(
+    name: &str,
+    labels: &[T],
+    input: &mut impl Iterator<Item = std::io::Result<String>>,
+) -> std::io::Result<Answer<T>> {
+    let choices = labels.iter().enumerate().map(|(i, label)| format!("{} {}", i + 1, label.to_string())).collect::<Vec<_>>();
+    loop {
+        print!("{name} [{}]: ", choices.join(", "));
+        stdout().flush()?;
+        let Some(line) = input.next().transpose()? else {
+            println!();
+            return Ok(Answer::Quit);
+        };
+        let answer = line.trim();
+        match answer.to_lowercase().as_str() {
+            "-" => return Ok(Answer::Unlabeled),
+            "s" => return Ok(Answer::Skip),
+            "q" => return Ok(Answer::Quit),
+            _ => {}
+        }
+        let label = match answer.parse::<usize>() {
+            Ok(number) => labels.get(number.wrapping_sub(1)).copied(),
+            Err(_) => labels.iter().find(|label| label.to_string().eq_ignore_ascii_case(answer)).copied(),
+        };
+        match label {
+            Some(label) => return Ok(Answer::Label(label)),
+            None => println!("\"{answer}\" is not one of the labels"),
+        }
+    }
+}
+
+fn print_agreement(gold: &[GoldLabel]) {
+    let agreements = agreement(gold);
+    if agreements.is_empty() {
+        println!("No texts were labeled by more than one annotator yet");
+        return;
+    }
+    println!("Agreement between annotators:");
+    for agreement in agreements {
+        let (first, second) = &agreement.annotators;
+        println!("  {first} and {second}:");
+        for (name, labels) in [("attitudes", &agreement.attitudes), ("subjects", &agreement.subjects)] {
+            if labels.texts > 0 {
+                println!("    {name:<9} {:.1}% agreed, kappa {:.3}, over {} texts", labels.observed * 100.0, labels.kappa, labels.texts);
+            }
+        }
+    }
+}
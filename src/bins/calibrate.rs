@@ -0,0 +1,95 @@
+use reddit_analyzer::*;
+use clap::Parser;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+   /// Name of the collected subreddit the labeled posts and comments are looked up in, for labels without their text
+   subreddit: Option<String>,
+   /// The hand-labeled posts and comments, as a JSON array, JSON lines or CSV, like the labels saved by `generate --labels`
+   #[arg(long)]
+   labels: PathBuf,
+   /// How to calibrate confidence values: "none", "temperature" or "isotonic"
+   #[arg(long, default_value_t = ConfidenceMethod::default())]
+   confidence: ConfidenceMethod,
+   /// How to classify text: with the "zero-shot" model, or the offline "lexicon" of keywords
+   #[arg(long, default_value_t = Backend::default())]
+   classifier: Backend,
+   /// The JSON taxonomy file the labels are from, if they are not the built-in attitudes and subjects
+   #[arg(long)]
+   taxonomy: Option<PathBuf>,
+   /// How many zero-shot model instances to run. Defaults from the CPU count and available memory
+   #[arg(long)]
+   models: Option<usize>,
+   /// How many texts to classify together
+   #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+   batch_size: usize,
+   /// Split texts longer than this many words into overlapping chunks. Use the same chunking as `analyze`,
+   /// so the thresholds are fitted to the scores it gives
+   #[arg(long)]
+   chunk_words: Option<usize>,
+   /// How many words each chunk shares with the one before it
+   #[arg(long, default_value_t = Chunking::default().overlap)]
+   chunk_overlap: usize,
+   /// How the scores of a long text's chunks are combined: "mean", "max" or "length-weighted"
+   #[arg(long, default_value_t = Aggregation::default())]
+   aggregation: Aggregation,
+   /// The file the calibration is saved to, for `analyze --calibration`
+   #[arg(long, default_value = "calibration.json")]
+   output: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if let Some(file) = &args.taxonomy {
+        Taxonomy::from_file(file).and_then(set_taxonomy).map_err(|err| format!("{err:?}"))?;
+    }
+    set_classifier(args.classifier.classifier(args.models).map_err(|err| format!("{err:?}"))?);
+    set_batch_size(args.batch_size);
+    let chunking = Chunking { window: args.chunk_words, overlap: args.chunk_overlap, aggregation: args.aggregation };
+    chunking.check()?;
+    set_chunking(chunking);
+
+    let gold = read_gold(&args.labels).map_err(|err| format!("{err:?}"))?;
+    let mut subreddit = Subreddit::default();
+    if let Some(name) = &args.subreddit {
+        subreddit.restore(Path::new(&format!("data/{}.json", canonical_name(name))));
+    }
+    let texts = subreddit.texts_by_id();
+    let labeled = labeled_texts(&gold, &texts);
+    if labeled.len() < gold.len() {
+        eprintln!("Left out {} labels whose text was not found", gold.len() - labeled.len());
+    }
+    if labeled.is_empty() {
+        return Err("no labeled texts to calibrate with".into());
+    }
+
+    println!("Classifying {} labeled texts...", labeled.len());
+    let examples = labeled
+        .par_chunks(batch_size())
+        .flat_map_iter(|batch| {
+            let analyses = analyze_batch(&batch.iter().map(|(text, _)| text.as_str()).collect::<Vec<_>>());
+            batch.iter().zip(analyses).filter_map(|((_, label), analysis)| {
+                Some((analysis.ok()?.scores?, label.attitude, label.subject))
+            }).collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let calibration = Calibration::fit(&examples, args.confidence);
+    for (name, labels) in [("Attitudes", &calibration.attitudes), ("Subjects", &calibration.subjects)] {
+        println!("{name}:");
+        for (label, fit) in &labels.thresholds {
+            println!("  {label:<24} threshold {:.3}  F1 {:.3}  ({} labeled)", fit.threshold, fit.f1, fit.support);
+        }
+        match &labels.confidence {
+            Confidence::None => {}
+            Confidence::Temperature { temperature } => println!("  confidence temperature {temperature:.3}"),
+            Confidence::Isotonic { points } => println!("  confidence calibrated with {} isotonic steps", points.len()),
+        }
+    }
+    calibration.save(&args.output)?;
+    println!("Saved the calibration of {} texts to {}", examples.len(), args.output.display());
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use reddit_analyzer::*;
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use log::LevelFilter;
+use std::{fs::OpenOptions, io::Write, path::PathBuf, sync::Arc, time::Duration};
 
 
 #[derive(Parser, Debug)]
@@ -7,10 +9,337 @@ use clap::Parser;
 struct Args {
    /// Name of subreddits to collect
    subreddits: Vec<String>,
+   /// URLs, permalinks or IDs of individual threads to collect
+   #[arg(long = "thread")]
+   threads: Vec<String>,
+   /// A file of thread URLs, permalinks or IDs to collect, one per line
+   #[arg(long)]
+   threads_file: Option<PathBuf>,
+   /// Collect the posts matching this search query, and their comments
+   #[arg(long)]
+   search: Option<String>,
+   /// Restrict the search to these subreddits
+   #[arg(long = "search-subreddit", requires = "search")]
+   search_subreddits: Vec<String>,
+   /// How far back to search: hour, day, week, month, year or all
+   #[arg(long, default_value = "all", requires = "search")]
+   time: TimeRange,
+   /// The most search results to collect
+   #[arg(long, default_value_t = 100, requires = "search")]
+   search_limit: u32,
+   /// The name of the collection the threads or search results are saved as.
+   /// Defaults to "threads" for threads, and "search" for search results.
+   /// Threads and search results are not collected under the name of a dataset that already exists, unless --refresh is given
+   #[arg(long)]
+   collection: Option<String>,
+   /// Names of users whose submitted posts and comments to collect
+   #[arg(long = "user")]
+   users: Vec<String>,
+   /// Keep running, polling the subreddits' newest posts and appending what is new to their datasets
+   #[arg(long)]
+   watch: bool,
+   /// How many seconds to wait between polls in watch mode
+   #[arg(long, default_value_t = 300, requires = "watch")]
+   interval: u64,
+   /// Posts younger than this many hours are revisited for new replies in watch mode
+   #[arg(long, default_value_t = 24.0, requires = "watch")]
+   revisit_hours: f64,
+   /// Analyze new posts and comments as they are found in watch mode,
+   /// appending the results to analysis/<subreddit>_watch_analysis.jsonl
+   #[arg(long, requires = "watch")]
+   analyze: bool,
+   /// How to classify text when analyzing: with the "zero-shot" model, or the offline "lexicon" of keywords
+   #[arg(long, default_value_t = Backend::default(), requires = "analyze")]
+   classifier: Backend,
+   /// Classify into the labels of this JSON taxonomy file when analyzing
+   #[arg(long, requires = "analyze")]
+   taxonomy: Option<PathBuf>,
+   /// Also give text every label that scores above the taxonomy's multi-label thresholds when analyzing
+   #[arg(long, requires = "analyze")]
+   multi_label: bool,
+   /// How many zero-shot model instances to run when analyzing. Defaults from the CPU count and available memory
+   #[arg(long, requires = "analyze")]
+   models: Option<usize>,
+   /// How many texts to classify together when analyzing
+   #[arg(long, default_value_t = DEFAULT_BATCH_SIZE, requires = "analyze")]
+   batch_size: usize,
+   /// Split texts longer than this many words into overlapping chunks when analyzing. Defaults to the most the classifier reads
+   #[arg(long, requires = "analyze")]
+   chunk_words: Option<usize>,
+   /// How many words each chunk shares with the one before it
+   #[arg(long, default_value_t = Chunking::default().overlap, requires = "analyze")]
+   chunk_overlap: usize,
+   /// How the scores of a long text's chunks are combined: "mean", "max" or "length-weighted"
+   #[arg(long, default_value_t = Aggregation::default(), requires = "analyze")]
+   aggregation: Aggregation,
+   /// Apply the per-label thresholds and confidence calibration saved by `calibrate` to this file when analyzing
+   #[arg(long, requires = "analyze")]
+   calibration: Option<PathBuf>,
+   /// Crawl breadth-first from the subreddits, collecting the related subreddits they link to
+   #[arg(long, conflicts_with = "watch")]
+   crawl: bool,
+   /// How many links away from the seed subreddits to crawl
+   #[arg(long, default_value_t = 2, requires = "crawl")]
+   max_depth: usize,
+   /// The most subreddits to collect while crawling, including the seeds
+   #[arg(long, default_value_t = 50, requires = "crawl")]
+   max_subreddits: usize,
+   /// Where to save the graph of discovered subreddits and the links between them
+   #[arg(long, default_value = "crawl.json", requires = "crawl")]
+   crawl_output: PathBuf,
+   /// Leave out posts marked not safe for work
+   #[arg(long)]
+   exclude_nsfw: bool,
+   /// Leave out stickied posts and comments, such as moderator announcements
+   #[arg(long)]
+   exclude_stickied: bool,
+   /// Leave out posts and comments whose body was removed or deleted
+   #[arg(long)]
+   exclude_removed: bool,
+   /// Leave out posts and comments by well known bots, such as AutoModerator
+   #[arg(long)]
+   exclude_bots: bool,
+   /// Leave out posts and comments by this account
+   #[arg(long = "bot")]
+   bots: Vec<String>,
+   /// Leave out posts and comments with a lower score
+   #[arg(long)]
+   min_score: Option<i32>,
+   /// Leave out posts with fewer comments
+   #[arg(long)]
+   min_comments: Option<u64>,
+   /// A JSON file with the script app credentials to collect with.
+   /// Without it, credentials are read from the REDDIT_* environment variables,
+   /// then from reddit-credentials.json, before falling back to anonymous access
+   #[arg(long)]
+   credentials: Option<PathBuf>,
+   /// How many subreddits to collect at once
+   #[arg(long, default_value_t = DEFAULT_WORKERS)]
+   workers: usize,
+   /// How many hot posts to collect from each subreddit
+   #[arg(long, default_value_t = ScraperConfig::default().posts_per_subreddit)]
+   posts: u32,
+   /// How many top-level comments to collect for each post
+   #[arg(long, default_value_t = ScraperConfig::default().comments_per_post)]
+   comments: u32,
+   /// The directory subreddits are saved to
+   #[arg(long, default_value = "data")]
+   output: PathBuf,
+   /// Collect subreddits again even if the output directory already has them
+   #[arg(long)]
+   refresh: bool,
+   /// The server to send API requests to, such as a local stand-in for reddit.com
+   #[arg(long)]
+   base_url: Option<String>,
+   /// Save every raw API response to this directory
+   #[arg(long, conflicts_with = "replay")]
+   record: Option<PathBuf>,
+   /// Serve every API response from this directory instead of the network
+   #[arg(long)]
+   replay: Option<PathBuf>,
+   /// Print progress as JSON lines on stdout instead of drawing progress bars, for cron jobs and CI
+   #[arg(long)]
+   json: bool,
+   /// Append a leveled log of the run to this file
+   #[arg(long)]
+   log_file: Option<PathBuf>,
+   /// The least severe messages written to the log file: error, warn, info, debug or trace
+   #[arg(long, default_value_t = LevelFilter::Info, requires = "log_file")]
+   log_level: LevelFilter,
 }
 
 fn main() -> Result<(), ScrapeError> {
     let args = Args::parse();
-    Vec::scrape(&args.subreddits)?;
+    if args.json {
+        enable_json_events();
+    }
+    if let Some(file) = &args.log_file {
+        init_log_file(file, args.log_level)?;
+    }
+
+    let client = match Credentials::load(args.credentials.as_deref())? {
+        Some(credentials) => Client::authenticated(credentials),
+        None => Client::default(),
+    };
+    let mut client = client.with_fixtures(match (args.record, args.replay) {
+        (Some(dir), _) => Fixtures::Record(dir),
+        (_, Some(dir)) => Fixtures::Replay(dir),
+        _ => Fixtures::Off,
+    });
+    if let Some(base_url) = args.base_url {
+        client = client.with_base_url(base_url);
+    }
+    Client::set_shared(client.clone());
+
+    let mut bots = args.bots;
+    if args.exclude_bots {
+        bots.extend(Filters::KNOWN_BOTS.iter().map(|bot| bot.to_string()));
+    }
+    let filters = Filters {
+        exclude_nsfw: args.exclude_nsfw,
+        exclude_stickied: args.exclude_stickied,
+        exclude_removed: args.exclude_removed,
+        bots,
+        min_score: args.min_score,
+        min_comments: args.min_comments,
+    };
+    Filters::set_shared(filters.clone());
+    let config = ScraperConfig {
+        posts_per_subreddit: args.posts,
+        comments_per_post: args.comments,
+        workers: args.workers,
+        skip_existing: !args.refresh,
+        filters,
+    };
+    let builder = || Scraper::builder().client(client.clone()).config(config.clone()).sink(DirectorySink::new(&args.output));
+    let scraper = |bars: &Arc<ProgressBars>| {
+        let bars = bars.clone();
+        builder().progress(move |progress| bars.update(progress)).build()
+    };
+
+    let mut threads = args.threads;
+    if let Some(file) = &args.threads_file {
+        threads.extend(read_threads(file)?);
+    }
+    if !threads.is_empty() {
+        let name = args.collection.as_deref().unwrap_or("threads");
+        let bars = Arc::new(ProgressBars::new(1, 1)?);
+        let collection = scraper(&bars).threads(name, &threads);
+        bars.finish();
+        for post in &collection?.failed_posts {
+            eprintln!("{name}: failed to collect post {}: {}", post.id, post.error);
+        }
+    }
+
+    if let Some(query) = args.search {
+        let search = Search {
+            query,
+            subreddits: args.search_subreddits,
+            time: args.time,
+            limit: args.search_limit,
+        };
+        let name = args.collection.as_deref().unwrap_or("search");
+        let bars = Arc::new(ProgressBars::new(1, 1)?);
+        let collection = scraper(&bars).search(name, &search);
+        bars.finish();
+        for post in &collection?.failed_posts {
+            eprintln!("{name}: failed to collect post {} \"{}\": {}", post.id, post.title, post.error);
+        }
+    }
+
+    let scraper_without_bars = builder().build();
+    for user in &args.users {
+        if let Err(err) = scraper_without_bars.user(user) {
+            eprintln!("u/{user}: failed to collect user: {err}");
+            emit(Event::Error { subreddit: format!("u/{user}"), id: None, error: err.to_string() });
+        }
+    }
+
+    if args.crawl {
+        let crawl = Crawl {
+            seeds: args.subreddits,
+            max_depth: args.max_depth,
+            max_subreddits: args.max_subreddits,
+        };
+        let graph = crawl.run(&scraper_without_bars, |subreddit, depth, err| match err {
+            None => {
+                log::info!("r/{subreddit} (depth {depth}): collected");
+                if !json_events() {
+                    println!("r/{subreddit} (depth {depth}): collected");
+                }
+            }
+            Some(err) => eprintln!("r/{subreddit} (depth {depth}): failed to collect subreddit: {err}"),
+        });
+        graph.save(&args.crawl_output);
+        log::info!("Discovered {} subreddits and {} links, saved to {}", graph.nodes.len(), graph.edges.len(), args.crawl_output.display());
+        if !json_events() {
+            println!("Discovered {} subreddits and {} links, saved to {}", graph.nodes.len(), graph.edges.len(), args.crawl_output.display());
+        }
+        return Ok(());
+    }
+
+    if args.watch {
+        if args.analyze {
+            if let Some(file) = &args.taxonomy {
+                if let Err(err) = Taxonomy::from_file(file).and_then(set_taxonomy) {
+                    Args::command().error(ErrorKind::InvalidValue, format!("could not use the taxonomy: {err:?}")).exit();
+                }
+            }
+            if let Some(file) = &args.calibration {
+                match Calibration::from_file(file) {
+                    Ok(calibration) => set_calibration(calibration),
+                    Err(err) => Args::command().error(ErrorKind::InvalidValue, format!("could not use the calibration: {err:?}")).exit(),
+                }
+            }
+            set_multi_label(args.multi_label);
+            set_batch_size(args.batch_size);
+            let chunking = Chunking { window: args.chunk_words, overlap: args.chunk_overlap, aggregation: args.aggregation };
+            if let Err(err) = chunking.check() {
+                Args::command().error(ErrorKind::ArgumentConflict, err).exit();
+            }
+            set_chunking(chunking);
+            match args.classifier.classifier(args.models) {
+                Ok(classifier) => set_classifier(classifier),
+                Err(err) => Args::command().error(ErrorKind::InvalidValue, format!("could not use the {} classifier: {err:?}", args.classifier)).exit(),
+            }
+        }
+        let watch = Watch {
+            subreddits: args.subreddits,
+            interval: Duration::from_secs(args.interval),
+            revisit_age: Duration::from_secs_f64(args.revisit_hours * 3600.0),
+            posts_per_poll: 25,
+        };
+        watch.run(&scraper_without_bars, |update| match update {
+            Ok(update) => {
+                log::info!("r/{}: {} new posts, {} new comments", update.subreddit, update.new_posts.len(), update.new_comments.len());
+                if !json_events() {
+                    println!("r/{}: {} new posts, {} new comments", update.subreddit, update.new_posts.len(), update.new_comments.len());
+                }
+                for post in &update.failed_posts {
+                    eprintln!("r/{}: failed to collect post {} \"{}\": {}", update.subreddit, post.id, post.title, post.error);
+                }
+                if args.analyze {
+                    if let Err(err) = analyze_update(update) {
+                        eprintln!("r/{}: failed to analyze update: {err}", update.subreddit);
+                        emit(Event::Error { subreddit: update.subreddit.clone(), id: None, error: err });
+                    }
+                }
+            }
+            Err((subreddit, err)) => {
+                eprintln!("r/{subreddit}: failed to poll: {err}");
+                emit(Event::Error { subreddit: subreddit.to_string(), id: None, error: err.to_string() });
+            }
+        });
+    }
+
+    let bars = Arc::new(ProgressBars::new(args.subreddits.len(), args.workers)?);
+    let (_, report) = scraper(&bars).run(&args.subreddits);
+    bars.finish();
+    if !report.is_complete() {
+        eprint!("{report}");
+    }
+    Ok(())
+}
+
+/// Analyze the new posts and comments found by a poll, appending one JSON line per item.
+fn analyze_update(update: &WatchUpdate) -> Result<(), String> {
+    let path = format!("analysis/{}_watch_analysis.jsonl", update.subreddit);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|err| format!("could not open {path}: {err}"))?;
+
+    let posts = update.new_posts.iter().map(|post| ("post", post.id.as_str(), post.analyze_submission()));
+    let comments = update.new_comments.iter().map(|comment| ("comment", comment.id.as_str(), comment.analyze_submission()));
+    for (kind, id, analysis) in posts.chain(comments) {
+        let line = serde_json::json!({
+            "subreddit": update.subreddit,
+            "kind": kind,
+            "id": id,
+            "analysis": analysis.map_err(|err| format!("{err:?}"))?,
+        });
+        writeln!(file, "{line}").map_err(|err| format!("could not write {path}: {err}"))?;
+    }
     Ok(())
 }
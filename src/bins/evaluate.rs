@@ -0,0 +1,135 @@
+use reddit_analyzer::*;
+use clap::Parser;
+use rayon::prelude::*;
+use std::{fs::write, path::{Path, PathBuf}};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+   /// Name of the collected subreddit the labeled posts and comments are looked up in, for labels without their text
+   subreddit: Option<String>,
+   /// The hand-labeled posts and comments, as a JSON array, JSON lines or CSV, like the labels saved by `generate --labels`
+   #[arg(long)]
+   labels: PathBuf,
+   /// How to classify text: with the "zero-shot" model, or the offline "lexicon" of keywords
+   #[arg(long, default_value_t = Backend::default())]
+   classifier: Backend,
+   /// The JSON taxonomy file the labels are from, if they are not the built-in attitudes and subjects
+   #[arg(long)]
+   taxonomy: Option<PathBuf>,
+   /// Evaluate with the per-label thresholds and confidence calibration saved by `calibrate` to this file
+   #[arg(long)]
+   calibration: Option<PathBuf>,
+   /// How many zero-shot model instances to run. Defaults from the CPU count and available memory
+   #[arg(long)]
+   models: Option<usize>,
+   /// How many texts to classify together
+   #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+   batch_size: usize,
+   /// Split texts longer than this many words into overlapping chunks. Use the same chunking as `analyze`,
+   /// so the evaluation measures the scores it gives
+   #[arg(long)]
+   chunk_words: Option<usize>,
+   /// How many words each chunk shares with the one before it
+   #[arg(long, default_value_t = Chunking::default().overlap)]
+   chunk_overlap: usize,
+   /// How the scores of a long text's chunks are combined: "mean", "max" or "length-weighted"
+   #[arg(long, default_value_t = Aggregation::default())]
+   aggregation: Aggregation,
+   /// How many of the most confidently misclassified texts to report
+   #[arg(long, default_value_t = 10)]
+   worst: usize,
+   /// The file the evaluation is saved to as JSON, to compare with other runs
+   #[arg(long, default_value = "evaluation.json")]
+   output: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if let Some(file) = &args.taxonomy {
+        Taxonomy::from_file(file).and_then(set_taxonomy).map_err(|err| format!("{err:?}"))?;
+    }
+    if let Some(file) = &args.calibration {
+        set_calibration(Calibration::from_file(file).map_err(|err| format!("{err:?}"))?);
+    }
+    set_classifier(args.classifier.classifier(args.models).map_err(|err| format!("{err:?}"))?);
+    set_batch_size(args.batch_size);
+    let chunking = Chunking { window: args.chunk_words, overlap: args.chunk_overlap, aggregation: args.aggregation };
+    chunking.check()?;
+    set_chunking(chunking);
+
+    let gold = read_gold(&args.labels).map_err(|err| format!("{err:?}"))?;
+    let mut subreddit = Subreddit::default();
+    if let Some(name) = &args.subreddit {
+        subreddit.restore(Path::new(&format!("data/{}.json", canonical_name(name))));
+    }
+    let labeled = labeled_texts(&gold, &subreddit.texts_by_id());
+    if labeled.len() < gold.len() {
+        eprintln!("Left out {} labels whose text was not found", gold.len() - labeled.len());
+    }
+    if labeled.is_empty() {
+        return Err("no labeled texts to evaluate with".into());
+    }
+
+    println!("Classifying {} labeled texts with the {} classifier...", labeled.len(), args.classifier);
+    let analyses = labeled
+        .par_chunks(batch_size())
+        .flat_map_iter(|batch| analyze_batch(&batch.iter().map(|(text, _)| text.as_str()).collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    let examples = labeled
+        .iter()
+        .zip(&analyses)
+        .filter_map(|((text, label), analysis)| Some((*label, text.as_str(), analysis.as_ref().ok()?)))
+        .collect::<Vec<_>>();
+    if examples.len() < labeled.len() {
+        eprintln!("Left out {} texts that could not be classified", labeled.len() - examples.len());
+    }
+
+    let evaluation = Evaluation::new(&args.classifier.to_string(), &args.labels.display().to_string(), &examples, args.worst);
+    print_report("Attitudes", &evaluation.attitudes);
+    print_report("Subjects", &evaluation.subjects);
+    write(&args.output, serde_json::to_string_pretty(&evaluation)?)?;
+    println!("Saved the evaluation to {}", args.output.display());
+    Ok(())
+}
+
+fn print_report(name: &str, report: &LabelReport) {
+    if report.examples == 0 {
+        return;
+    }
+    println!();
+    println!("{name} ({} labeled texts, {:.1}% accuracy)", report.examples, report.accuracy * 100.0);
+    println!("  {:<24} {:>9} {:>9} {:>9} {:>9}", "label", "precision", "recall", "F1", "support");
+    for metrics in &report.labels {
+        println!("  {:<24} {:>9.3} {:>9.3} {:>9.3} {:>9}", metrics.label, metrics.precision, metrics.recall, metrics.f1, metrics.support);
+    }
+    for (average, averages) in [("macro average", &report.macro_average), ("micro average", &report.micro_average)] {
+        println!("  {:<24} {:>9.3} {:>9.3} {:>9.3}", average, averages.precision, averages.recall, averages.f1);
+    }
+
+    println!();
+    println!("  Confusion matrix, gold labels down and predicted labels across:");
+    let short = |label: &str| label.chars().take(6).collect::<String>();
+    print!("  {:<24}", "");
+    for label in &report.confusion.labels {
+        print!(" {:>6}", short(label));
+    }
+    println!();
+    for (label, row) in report.confusion.labels.iter().zip(&report.confusion.counts) {
+        print!("  {label:<24}");
+        for count in row {
+            print!(" {count:>6}");
+        }
+        println!();
+    }
+
+    if !report.worst.is_empty() {
+        println!();
+        println!("  Most confidently misclassified:");
+        for example in &report.worst {
+            let text = example.text.replace('\n', " ");
+            let text = if text.chars().count() > 80 { format!("{}...", text.chars().take(77).collect::<String>()) } else { text };
+            println!("  {} {} as {} ({:.2}): {text}", example.id, example.gold, example.predicted, example.confidence);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use reddit_analyzer::*;
+use clap::Parser;
+use std::{fs::{create_dir_all, write}, path::PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+   /// Name of the synthetic subreddit to generate
+   name: String,
+   /// Everything random is drawn from this seed, so the same seed always generates the same subreddit
+   #[arg(long, default_value_t = 0)]
+   seed: u64,
+   /// How many posts to generate
+   #[arg(long, default_value_t = Synthetic::default().posts)]
+   posts: usize,
+   /// The average number of top-level comments on a post
+   #[arg(long, default_value_t = Synthetic::default().comments_per_post)]
+   comments: f64,
+   /// The average number of replies to a top-level comment
+   #[arg(long, default_value_t = Synthetic::default().branching)]
+   branching: f64,
+   /// How much the average number of replies shrinks at each level deeper, between 0 and 1
+   #[arg(long, default_value_t = Synthetic::default().depth_decay)]
+   depth_decay: f64,
+   /// The deepest a reply can be nested
+   #[arg(long, default_value_t = Synthetic::default().max_depth)]
+   max_depth: usize,
+   /// The median score of posts and comments
+   #[arg(long, default_value_t = Synthetic::default().score_median)]
+   score_median: f64,
+   /// How spread out scores are around the median, on a log scale
+   #[arg(long, default_value_t = Synthetic::default().score_spread)]
+   score_spread: f64,
+   /// The share of posts that are links or images, with a title but no body
+   #[arg(long, default_value_t = Synthetic::default().link_posts)]
+   link_posts: f64,
+   /// The directory the subreddit is saved to, as data/<name>.json like collected subreddits
+   #[arg(long, default_value = "data")]
+   output: PathBuf,
+   /// Also save the attitude and subject each post and comment was written with to this file
+   #[arg(long)]
+   labels: Option<PathBuf>,
+   /// Overwrite a dataset that already has the name, even if it was collected from Reddit
+   #[arg(long)]
+   force: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let output = args.output.join(format!("{}.json", canonical_name(&args.name)));
+    if output.exists() && !args.force {
+        return Err(format!("{} already exists, generate the subreddit under another name or pass --force to overwrite it", output.display()).into());
+    }
+    let synthetic = Synthetic {
+        name: args.name,
+        seed: args.seed,
+        posts: args.posts,
+        comments_per_post: args.comments,
+        branching: args.branching,
+        depth_decay: args.depth_decay,
+        max_depth: args.max_depth,
+        score_median: args.score_median,
+        score_spread: args.score_spread,
+        link_posts: args.link_posts,
+        subjects: vec![],
+    };
+    let (subreddit, labels) = synthetic.generate_labeled();
+
+    create_dir_all(&args.output)?;
+    subreddit.save(&output);
+    if let Some(file) = &args.labels {
+        write(file, serde_json::to_string(&labels)?)?;
+    }
+    println!("Generated r/{} with {} posts and {} comments, saved to {}", subreddit.name, subreddit.posts.len(), labels.len() - subreddit.posts.len(), output.display());
+    Ok(())
+}
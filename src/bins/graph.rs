@@ -1,33 +1,75 @@
 use reddit_analyzer::*;
-use std::collections::HashMap;
+use clap::Parser;
+use std::collections::{BTreeMap, HashMap};
 use plotters::{prelude::*, style::colors::full_palette::PURPLE, coord::Shift};
 
 #[derive(Debug)]
 struct SubredditData {
     subreddit: Subreddit,
     analysis: Vec<SubmissionAnalysis>,
+    /// Count every multi-label attitude and subject of a submission, instead of only its single label.
+    multi_label: bool,
 }
 
 fn traverse<T>(analysis: &SubmissionAnalysis, f: &impl Fn(&SubmissionAnalysis) -> T) -> Vec<T> {
-    let mut result: Vec<T> = analysis.children.iter().map(|child| traverse(child, f)).flatten().collect();
+    let mut result: Vec<T> = analysis.children.iter().flat_map(|child| traverse(child, f)).collect();
     result.push(f(analysis));
     result
 }
 
 impl SubredditData {
-    fn new(subreddit_name: &str) -> Self {
+    /// Split a collection with posts from many subreddits, such as search results,
+    /// into one `SubredditData` per subreddit, named `collection:subreddit`.
+    fn split(collection_name: &str, content: PostContent, multi_label: bool) -> Vec<Self> {
+        let collection = Self::new(collection_name, content, multi_label);
+        let mut by_subreddit = BTreeMap::<String, Vec<SubmissionAnalysis>>::new();
+        for analysis in collection.analysis {
+            let subreddit = analysis.subreddit.clone().unwrap_or_else(|| collection_name.to_string());
+            by_subreddit.entry(subreddit).or_default().push(analysis);
+        }
+
+        by_subreddit
+            .into_iter()
+            .map(|(subreddit_name, analysis)| Self {
+                subreddit: Subreddit {
+                    name: format!("{collection_name}:{subreddit_name}"),
+                    description: collection.subreddit.description.clone(),
+                    ..Subreddit::default()
+                },
+                analysis,
+                multi_label,
+            })
+            .collect()
+    }
+
+    /// Load a subreddit and its analysis, with `content` standing for each post.
+    fn new(subreddit_name: &str, content: PostContent, multi_label: bool) -> Self {
         let mut subreddit = Subreddit::default();
-        subreddit.restore(std::path::Path::new(format!("data/{subreddit_name}.json").as_str()));
+        subreddit.restore(std::path::Path::new(format!("data/{}.json", canonical_name(subreddit_name)).as_str()));
 
         let mut analysis: Vec<SubmissionAnalysis> = vec![];
         analysis.restore(std::path::Path::new(format!("analysis/{subreddit_name}_subreddit_analysis.json").as_str()));
+        for post in &mut analysis {
+            post.use_post_content(content);
+        }
 
         Self {
             subreddit,
             analysis,
+            multi_label,
         }
     }
 
+    /// The attitudes a submission is counted under.
+    fn attitudes(&self, analysis: &Analysis) -> Vec<Attitude> {
+        if self.multi_label { analysis.attitudes() } else { vec![analysis.attitude] }
+    }
+
+    /// The subjects a submission is counted under.
+    fn subjects(&self, analysis: &Analysis) -> Vec<Subject> {
+        if self.multi_label { analysis.subjects() } else { vec![analysis.subject] }
+    }
+
     fn total_comments(&self) -> usize {
         self.analysis.iter().map(|a| a.size()).sum()
     }
@@ -91,7 +133,7 @@ impl SubredditData {
         self.analysis
             .iter()
             .map(|submission| {
-                traverse(submission, &|analysis| analysis.analysis.attitude == Attitude::Mocking || analysis.analysis.subject == Subject::Joke)
+                traverse(submission, &|analysis| self.attitudes(&analysis.analysis).contains(&Attitude::Mocking) || self.subjects(&analysis.analysis).contains(&Subject::Joke))
                     .into_iter()    
                     .filter(|a| *a)
                     .count()
@@ -101,7 +143,7 @@ impl SubredditData {
 
     fn subject_distribution(&self) -> HashMap<Subject, usize> {
         let distribution = std::sync::Mutex::new(HashMap::new());
-        for subject in Subject::VALUES {
+        for subject in Subject::all() {
             let mut distribution = distribution.lock().unwrap();
             distribution.insert(subject, 0);
         }
@@ -109,7 +151,9 @@ impl SubredditData {
         for submission in &self.analysis {
             traverse(submission, &|analysis| {
                 let mut distribution = distribution.lock().unwrap();
-                distribution.entry(analysis.analysis.subject).and_modify(|count| *count += 1);
+                for subject in self.subjects(&analysis.analysis) {
+                    distribution.entry(subject).and_modify(|count| *count += 1);
+                }
             });
         }
 
@@ -119,7 +163,7 @@ impl SubredditData {
 
     fn attitude_distribution(&self) -> HashMap<Attitude, usize> {
         let distribution = std::sync::Mutex::new(HashMap::new());
-        for subject in Attitude::VALUES {
+        for subject in Attitude::all() {
             let mut distribution = distribution.lock().unwrap();
             distribution.insert(subject, 0);
         }
@@ -127,7 +171,9 @@ impl SubredditData {
         for submission in &self.analysis {
             traverse(submission, &|analysis| {
                 let mut distribution = distribution.lock().unwrap();
-                distribution.entry(analysis.analysis.attitude).and_modify(|count| *count += 1);
+                for attitude in self.attitudes(&analysis.analysis) {
+                    distribution.entry(attitude).and_modify(|count| *count += 1);
+                }
             });
         }
 
@@ -137,8 +183,8 @@ impl SubredditData {
 
     fn attitude_per_subject_distribution(&self) -> HashMap<(Subject, Attitude), usize> {
         let distribution = std::sync::Mutex::new(HashMap::new());
-        for subject in Subject::VALUES {
-            for attitude in Attitude::VALUES {
+        for subject in Subject::all() {
+            for attitude in Attitude::all() {
                 let mut distribution = distribution.lock().unwrap();
                 distribution.insert((subject, attitude), 0);
             }
@@ -147,7 +193,11 @@ impl SubredditData {
         for submission in &self.analysis {
             traverse(submission, &|analysis| {
                 let mut distribution = distribution.lock().unwrap();
-                distribution.entry((analysis.analysis.subject, analysis.analysis.attitude)).and_modify(|count| *count += 1);
+                for subject in self.subjects(&analysis.analysis) {
+                    for attitude in self.attitudes(&analysis.analysis) {
+                        distribution.entry((subject, attitude)).and_modify(|count| *count += 1);
+                    }
+                }
             });
         }
 
@@ -173,8 +223,19 @@ pub struct Stats {
 }
 
 impl Stats {
-    fn new(subreddit_name: &str) -> Self {
-        let data = SubredditData::new(subreddit_name);
+    fn new(subreddit_name: &str, content: PostContent, multi_label: bool) -> Self {
+        Self::from_data(subreddit_name, SubredditData::new(subreddit_name, content, multi_label))
+    }
+
+    /// The stats of each subreddit in a collection with posts from many subreddits.
+    fn split(collection_name: &str, content: PostContent, multi_label: bool) -> Vec<Self> {
+        SubredditData::split(collection_name, content, multi_label)
+            .into_iter()
+            .map(|data| Self::from_data(&data.subreddit.name.clone(), data))
+            .collect()
+    }
+
+    fn from_data(subreddit_name: &str, data: SubredditData) -> Self {
         Self {
             subreddit_name: subreddit_name.to_string(),
             subscribers: data.subreddit.subscribers as usize,
@@ -352,17 +413,18 @@ fn plot_overall_submission_breakdown(stats: &[Stats]) -> Result<(), Box<dyn std:
     let dims = subject_area.dim_in_pixel();
     let center = (dims.0 as i32 / 2, dims.1 as i32 / 2);
     let radius = 375.0;
-    let mut sizes_and_labels = Subject::VALUES.iter().map(
+    let subjects = Subject::all();
+    let mut sizes_and_labels = subjects.iter().map(
         |s| (stats.iter().map(|x| x.subject_distribution.get(s).unwrap_or(&0)).sum::<usize>() as f64, s.to_string())
-    ).filter(|(_, label)| label != "Other").collect::<Vec<_>>();
+    ).filter(|(_, label)| *label != Subject::fallback().to_string()).collect::<Vec<_>>();
 
-    sizes_and_labels.sort_by(|(size1, _), (size2, _)| match size2.partial_cmp(&size1) {
+    sizes_and_labels.sort_by(|(size1, _), (size2, _)| match size2.partial_cmp(size1) {
         Some(c) => c,
         None => std::cmp::Ordering::Equal,
     });
 
     let (sizes, labels) = sizes_and_labels.iter().cloned().unzip::<_, _, Vec<_>, Vec<_>>();
-    let colors = Subject::VALUES.iter().enumerate().map(|(i, _)| hsv_to_rgb(i as f64 / Subject::VALUES.len() as f64, 1.0, 1.0)).collect::<Vec<_>>();
+    let colors = subjects.iter().enumerate().map(|(i, _)| hsv_to_rgb(i as f64 / subjects.len() as f64, 1.0, 1.0)).collect::<Vec<_>>();
     let mut pie = Pie::new(&center, &radius, &sizes, &colors, &labels);
     pie.start_angle(10.0);
     pie.label_offset(radius * 0.075);
@@ -373,17 +435,18 @@ fn plot_overall_submission_breakdown(stats: &[Stats]) -> Result<(), Box<dyn std:
     let dims = attitude_area.dim_in_pixel();
     let center = (dims.0 as i32 * 3 / 2, dims.1 as i32 / 2);
     let radius = 375.0;
-    let mut sizes_and_labels = Attitude::VALUES.iter().map(
+    let attitudes = Attitude::all();
+    let mut sizes_and_labels = attitudes.iter().map(
         |s| (stats.iter().map(|x| x.attitude_distribution.get(s).unwrap_or(&0)).sum::<usize>() as f64, s.to_string())
-    ).filter(|(_, label)| label != "Neutral").collect::<Vec<_>>();
+    ).filter(|(_, label)| *label != Attitude::fallback().to_string()).collect::<Vec<_>>();
 
-    sizes_and_labels.sort_by(|(size1, _), (size2, _)| match size2.partial_cmp(&size1) {
+    sizes_and_labels.sort_by(|(size1, _), (size2, _)| match size2.partial_cmp(size1) {
         Some(c) => c,
         None => std::cmp::Ordering::Equal,
     });
 
     let (sizes, labels) = sizes_and_labels.iter().cloned().unzip::<_, _, Vec<_>, Vec<_>>();
-    let colors = Attitude::VALUES.iter().enumerate().map(|(i, _)| hsv_to_rgb(i as f64 / Attitude::VALUES.len() as f64 / 1.33 + 0.4, 1.0, 1.0)).collect::<Vec<_>>();
+    let colors = attitudes.iter().enumerate().map(|(i, _)| hsv_to_rgb(i as f64 / attitudes.len() as f64 / 1.33 + 0.4, 1.0, 1.0)).collect::<Vec<_>>();
     let mut pie = Pie::new(&center, &radius, &sizes, &colors, &labels);
     pie.start_angle(10.0);
     pie.label_offset(radius * 0.075);
@@ -395,66 +458,62 @@ fn plot_overall_submission_breakdown(stats: &[Stats]) -> Result<(), Box<dyn std:
     Ok(())
 }
 
+/// The colors of the built-in attitudes, and evenly spread colors for custom ones.
+fn attitude_colors(attitudes: &[Attitude]) -> HashMap<Attitude, RGBColor> {
+    let rgb = RGBColor;
+    let mut colors = HashMap::new();
+    colors.insert(Attitude::Inquisitive, rgb(255, 241, 118));
+    colors.insert(Attitude::Praise, rgb(27, 118, 255));
+    colors.insert(Attitude::Condemnation, rgb(255, 29, 35));
+    colors.insert(Attitude::Agreement, rgb(14, 234, 255));
+    colors.insert(Attitude::Complaint, rgb(255, 109, 31));
+    colors.insert(Attitude::Mocking, rgb(219, 165, 7));
+    colors.insert(Attitude::Disagreement, rgb(210, 54, 0));
+    colors.insert(Attitude::Annoyed, rgb(144, 11, 10));
+    colors.insert(Attitude::Neutral, rgb(255, 255, 255));
+    for (i, attitude) in attitudes.iter().enumerate() {
+        colors.entry(*attitude).or_insert_with(|| hsv_to_rgb(i as f64 / attitudes.len() as f64, 1.0, 1.0));
+    }
+    colors
+}
+
 fn plot_breakdown_by_subject(stats: &[Stats]) -> Result<(), Box<dyn std::error::Error>> {
     let root_area = SVGBackend::new("graphs/subject-breakdown.svg", (3800, 2900)).into_drawing_area();
     root_area.fill(&WHITE)?;
     let root_area = root_area.margin(50, 0, 0, 0);
     root_area.titled("Attitudes of Submissions Towards Subjects", ("sans-serif", 120))?;
     let root_area = root_area.margin(200, 0, 100, 100);
-    let areas: HashMap<Subject, DrawingArea<SVGBackend, Shift>> = HashMap::from_iter(Subject::VALUES.into_iter().filter(|s| *s != Subject::Other).zip(root_area.split_evenly((3, Subject::VALUES.len() / 3))));
+    let (subjects, attitudes) = (Subject::all(), Attitude::all());
+    let (other, neutral) = (Subject::fallback(), Attitude::fallback());
+    let labeled_subjects = subjects.iter().copied().filter(|s| *s != other).collect::<Vec<_>>();
+    let areas: HashMap<Subject, DrawingArea<SVGBackend, Shift>> = HashMap::from_iter(labeled_subjects.iter().copied().zip(root_area.split_evenly((3, labeled_subjects.len().div_ceil(3)))));
     let mut distribution = HashMap::<(Subject, Attitude), usize>::new();
 
     // Get the distribution of attitudes for this subject
     for stat in stats {
-        for subject in Subject::VALUES {
-            for attitude in Attitude::VALUES {
+        for subject in subjects.iter().copied() {
+            for attitude in attitudes.iter().copied() {
                 *distribution.entry((subject, attitude)).or_insert(0) += stat.attitude_per_subject_distribution.get(&(subject, attitude)).unwrap_or(&0);
             }
         }
     }
 
-    // let colors = Attitude::VALUES.iter().enumerate().map(|(i, _)| hsv_to_rgb(i as f64 / Subject::VALUES.len() as f64, 1.0, 1.0)).collect::<Vec<_>>();
-    let mut colors = HashMap::new();
-    // for (i, attitude) in Attitude::VALUES.iter().enumerate() {
-    //     colors.insert(attitude, hsv_to_rgb(i as f64 / Attitude::VALUES.len() as f64, 1.0, 1.0));
-    // }
-
-    // Inquisitive,
-    // Praise,
-    // Condemnation,
-    // Agreement,
-    // Complaint,
-    // Mocking,
-    // Disagreement,
-    // Annoyed,
-    let rgb = RGBColor;
-    colors.insert(&Attitude::Inquisitive, rgb(255, 241, 118));
-    colors.insert(&Attitude::Praise, rgb(27, 118, 255));
-    colors.insert(&Attitude::Condemnation, rgb(255, 29, 35));
-    colors.insert(&Attitude::Agreement, rgb(14, 234, 255));
-    colors.insert(&Attitude::Complaint, rgb(255, 109, 31));
-    colors.insert(&Attitude::Mocking, rgb(219, 165, 7));
-    colors.insert(&Attitude::Disagreement, rgb(210, 54, 0));
-    colors.insert(&Attitude::Annoyed, rgb(144, 11, 10));
-    colors.insert(&Attitude::Neutral, rgb(255, 255, 255));
-
-    for subject in Subject::VALUES {
-        if subject == Subject::Other {
-            continue;
-        }
+    let colors = attitude_colors(&attitudes);
+
+    for subject in labeled_subjects {
         let area = areas.get(&subject).unwrap().margin(35, 35, 35, 35);
-        area.titled(&format!("Attitudes Towards {}", subject.to_string()), ("sans-serif", 60))?;
+        area.titled(&format!("Attitudes Towards {}", subject), ("sans-serif", 60))?;
 
         let dims = area.dim_in_pixel();
         let plotters::coord::Shift(pos) = area.as_coord_spec();
 
         let center = (pos.0 + dims.0 as i32 / 2, pos.1 + dims.1 as i32 / 2);
         let radius = 250.0;
-        let mut sizes_labels_and_colors = Attitude::VALUES.iter().map(
+        let mut sizes_labels_and_colors = attitudes.iter().filter(|attitude| **attitude != neutral).map(
             |attitude| (*distribution.get(&(subject, *attitude)).unwrap_or(&0), attitude.to_string(), *colors.get(attitude).unwrap())
-        ).filter(|(size, label, _)| *size >= 1 && label != "Neutral").collect::<Vec<_>>();
+        ).filter(|(size, _, _)| *size >= 1).collect::<Vec<_>>();
     
-        sizes_labels_and_colors.sort_by(|(size1, _, _), (size2, _, _)| match size2.partial_cmp(&size1) {
+        sizes_labels_and_colors.sort_by(|(size1, _, _), (size2, _, _)| match size2.partial_cmp(size1) {
             Some(c) => c,
             None => std::cmp::Ordering::Equal,
         });
@@ -477,9 +536,10 @@ fn plot_breakdown_by_subject(stats: &[Stats]) -> Result<(), Box<dyn std::error::
 fn plot_reddit_surface(stats: &[Stats]) -> Result<(), Box<dyn std::error::Error>> {
     // let root_area = SVGBackend::new("graphs/surface.svg", (1024, 800)).into_drawing_area();
 
-    let x_dim = Subject::VALUES.len() as i32 - 1;
+    let (subjects, attitudes) = (Subject::all(), Attitude::all());
+    let x_dim = subjects.len() as i32 - 1;
     let y_dim = 1000;
-    let z_dim = Attitude::VALUES.len() as i32 - 1;
+    let z_dim = attitudes.len() as i32 - 1;
 
     let root_area = BitMapBackend::gif("graphs/subject-attitude-occurences.gif", (1024, 800), 100)?.into_drawing_area();
     root_area.fill(&WHITE)?;
@@ -488,8 +548,8 @@ fn plot_reddit_surface(stats: &[Stats]) -> Result<(), Box<dyn std::error::Error>
     let root_area = root_area.margin(70, 0, 0, 0);
 
     let mut lookup_table = HashMap::new();
-    for subject in Subject::VALUES {
-        for attitude in Attitude::VALUES {
+    for subject in subjects.iter().copied() {
+        for attitude in attitudes.iter().copied() {
             lookup_table.insert((subject, attitude), stats.iter().map(|stat| stat.attitude_per_subject_distribution.get(&(subject, attitude)).unwrap_or(&0)).sum::<usize>() as i32);
         }
     }
@@ -516,11 +576,11 @@ fn plot_reddit_surface(stats: &[Stats]) -> Result<(), Box<dyn std::error::Error>
         ]
         .map(|(label, position)| Text::new(label, position, &axis_title_style))).unwrap();
         chart_context.draw_series(SurfaceSeries::xoz(
-            (1..=x_dim).map(|v| v),
-            (1..=z_dim).map(|v| v),
+            1..=x_dim,
+            1..=z_dim,
             |x:i32,z:i32| {
-                let subject = Subject::VALUES[x as usize];
-                let attitude = Attitude::VALUES[z as usize];
+                let subject = subjects[x as usize];
+                let attitude = attitudes[z as usize];
                 *lookup_table.get(&(subject, attitude)).unwrap_or(&0)
             }
         ).style_func(
@@ -536,9 +596,9 @@ fn plot_reddit_surface(stats: &[Stats]) -> Result<(), Box<dyn std::error::Error>
             .axis_panel_style(PURPLE.mix(0.1))
             .bold_grid_style(BLACK.mix(0.3))
             .light_grid_style(BLUE.mix(0.2))
-            .x_formatter(&|x| format!("{:?}", Subject::VALUES[*x as usize]))
+            .x_formatter(&|x| subjects[*x as usize].to_string())
             .y_formatter(&|y| format!("{y}"))
-            .z_formatter(&|z| format!("{:?}", Attitude::VALUES[*z as usize]))
+            .z_formatter(&|z| attitudes[*z as usize].to_string())
             .draw()?;
 
         root_area.present()?;
@@ -574,21 +634,12 @@ fn plot_subreddit_breakdown(stats: &[Stats], subreddits: &[&[&str]]) -> Result<(
             areas.insert((row, col * 2 + 1), attitude_area);
         }
     }
-    let mut attitude_colors = HashMap::new();
-    let rgb = RGBColor;
-    attitude_colors.insert(&Attitude::Inquisitive, rgb(255, 241, 118));
-    attitude_colors.insert(&Attitude::Praise, rgb(27, 118, 255));
-    attitude_colors.insert(&Attitude::Condemnation, rgb(255, 29, 35));
-    attitude_colors.insert(&Attitude::Agreement, rgb(14, 234, 255));
-    attitude_colors.insert(&Attitude::Complaint, rgb(255, 109, 31));
-    attitude_colors.insert(&Attitude::Mocking, rgb(219, 165, 7));
-    attitude_colors.insert(&Attitude::Disagreement, rgb(210, 54, 0));
-    attitude_colors.insert(&Attitude::Annoyed, rgb(144, 11, 10));
-    attitude_colors.insert(&Attitude::Neutral, rgb(255, 255, 255));
-
+    let (subjects, attitudes) = (Subject::all(), Attitude::all());
+    let (other, neutral) = (Subject::fallback(), Attitude::fallback());
+    let attitude_colors = attitude_colors(&attitudes);
     let mut subject_colors = HashMap::new();
-    for (i, subject) in Subject::VALUES.into_iter().enumerate() {
-        subject_colors.insert(subject, hsv_to_rgb(i as f64 / Subject::VALUES.len() as f64, 1.0, 1.0));
+    for (i, subject) in subjects.iter().enumerate() {
+        subject_colors.insert(*subject, hsv_to_rgb(i as f64 / subjects.len() as f64, 1.0, 1.0));
     }
 
     for (row, row_of_subreddits) in subreddits.iter().enumerate() {
@@ -604,10 +655,10 @@ fn plot_subreddit_breakdown(stats: &[Stats], subreddits: &[&[&str]]) -> Result<(
             let radius = 500.0;
             let center = (pos.0 + dims.0 as i32 / 2, pos.1 + dims.1 as i32 / 2);
 
-            let mut sizes_labels_and_colors = Subject::VALUES.iter().map(
-                |s| (*subreddit_stats.get(&subreddit.to_string()).unwrap().subject_distribution.get(s).unwrap_or(&0) as f64, s.to_string(), subject_colors.get(s).unwrap())
-            ).filter(|(size, label, _)| *size > 0.0 && label != "Other").collect::<Vec<_>>();
-            sizes_labels_and_colors.sort_by(|(size1, _, _), (size2, _, _)| match size2.partial_cmp(&size1) {
+            let mut sizes_labels_and_colors = subjects.iter().filter(|s| **s != other).map(
+                |s| (*subreddit_stats.get(*subreddit).unwrap().subject_distribution.get(s).unwrap_or(&0) as f64, s.to_string(), subject_colors.get(s).unwrap())
+            ).filter(|(size, _, _)| *size > 0.0).collect::<Vec<_>>();
+            sizes_labels_and_colors.sort_by(|(size1, _, _), (size2, _, _)| match size2.partial_cmp(size1) {
                 Some(c) => c,
                 None => std::cmp::Ordering::Equal,
             });
@@ -624,10 +675,10 @@ fn plot_subreddit_breakdown(stats: &[Stats], subreddits: &[&[&str]]) -> Result<(
             let plotters::coord::Shift(pos) = attitude_area.as_coord_spec();
             let center = (pos.0 + dims.0 as i32 / 2, pos.1 + dims.1 as i32 / 2);
 
-            let mut sizes_labels_and_colors = Attitude::VALUES.iter().map(
-                |a| (*subreddit_stats.get(&subreddit.to_string()).unwrap().attitude_distribution.get(a).unwrap_or(&0) as f64, a.to_string(), attitude_colors.get(a).unwrap())
-            ).filter(|(size, label, _)| *size > 0.0 && label != "Neutral").collect::<Vec<_>>();
-            sizes_labels_and_colors.sort_by(|(size1, _, _), (size2, _, _)| match size2.partial_cmp(&size1) {
+            let mut sizes_labels_and_colors = attitudes.iter().filter(|a| **a != neutral).map(
+                |a| (*subreddit_stats.get(*subreddit).unwrap().attitude_distribution.get(a).unwrap_or(&0) as f64, a.to_string(), attitude_colors.get(a).unwrap())
+            ).filter(|(size, _, _)| *size > 0.0).collect::<Vec<_>>();
+            sizes_labels_and_colors.sort_by(|(size1, _, _), (size2, _, _)| match size2.partial_cmp(size1) {
                 Some(c) => c,
                 None => std::cmp::Ordering::Equal,
             });
@@ -648,15 +699,41 @@ fn plot_subreddit_breakdown(stats: &[Stats], subreddits: &[&[&str]]) -> Result<(
 }
 
 
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+   /// Collections with posts from many subreddits, such as search results,
+   /// to graph as one entry per subreddit instead of a single entry
+   #[arg(long)]
+   split: Vec<String>,
+   /// What stands for each post: its title and body analyzed together ("combined"), its "title", or its "body".
+   /// A title or body is only analyzed on its own by `analyze --post-content`, and falls back to "combined" otherwise
+   #[arg(long, default_value_t = PostContent::Combined)]
+   post_content: PostContent,
+   /// The JSON file of the custom taxonomy the subreddits were analyzed with
+   #[arg(long)]
+   taxonomy: Option<std::path::PathBuf>,
+   /// Count every label submissions were given in multi-label mode, instead of only their single label
+   #[arg(long)]
+   multi_label: bool,
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if let Some(file) = &args.taxonomy {
+        Taxonomy::from_file(file).and_then(set_taxonomy).map_err(|err| format!("{err:?}"))?;
+    }
     let paths = std::fs::read_dir("./data").unwrap();
     let mut stats = vec![];
     for path in paths {
         let path = path.unwrap().path();
         let name = path.file_stem().unwrap().to_str().unwrap();
         if std::path::Path::new(&format!("analysis/{}_subreddit_analysis.json", name)).exists() {
-            let stat = Stats::new(name);
-            stats.push(stat);
+            if args.split.iter().any(|collection| collection == name) {
+                stats.extend(Stats::split(name, args.post_content, args.multi_label));
+            } else {
+                stats.push(Stats::new(name, args.post_content, args.multi_label));
+            }
         }
     }
 
@@ -0,0 +1,115 @@
+use indicatif::ProgressDrawTarget;
+use lazy_static::lazy_static;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use std::{fs::{File, OpenOptions}, io::{self, Write}, path::Path, sync::{atomic::{AtomicBool, Ordering}, Mutex}, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+/// Something that happened during a collection or analysis run.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Started collecting a subreddit or collection.
+    SubredditStarted { subreddit: String },
+    /// Collected a post and its comments.
+    PostFetched { subreddit: String, id: String, comments: usize, elapsed_ms: u128 },
+    /// Finished collecting a subreddit or collection.
+    SubredditFinished { subreddit: String, posts: usize, failed_posts: usize, filtered_posts: usize, elapsed_ms: u128 },
+    /// Something could not be collected or analyzed.
+    Error { subreddit: String, id: Option<String>, error: String },
+    /// Analyzed a post and its comments.
+    PostAnalyzed { subreddit: String, index: usize, submissions: usize, elapsed_ms: u128 },
+    /// Finished analyzing a subreddit or user.
+    AnalysisDone { subreddit: String, posts: usize, submissions: usize, elapsed_ms: u128 },
+    /// How the classifier's inference workers kept up during an analysis.
+    InferenceMetrics { subreddit: String, workers: usize, completed: usize, failed: usize, texts_per_sec: f64, utilization: f64 },
+}
+
+impl Event {
+    fn level(&self) -> Level {
+        match self {
+            Self::Error { .. } => Level::Error,
+            Self::PostFetched { .. } | Self::PostAnalyzed { .. } => Level::Debug,
+            _ => Level::Info,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    /// When the event happened, in seconds since the Unix epoch.
+    time: f64,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+static JSON_EVENTS: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref STDOUT_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Print every event as a line of JSON on stdout, and hide the progress bars.
+/// This is meant for unattended runs, such as cron jobs and CI.
+pub fn enable_json_events() {
+    JSON_EVENTS.store(true, Ordering::SeqCst);
+}
+
+/// Are events being printed as JSON lines?
+pub fn json_events() -> bool {
+    JSON_EVENTS.load(Ordering::SeqCst)
+}
+
+/// Where progress bars should draw: nowhere if events are printed as JSON lines, otherwise stderr.
+pub fn draw_target() -> ProgressDrawTarget {
+    if json_events() {
+        ProgressDrawTarget::hidden()
+    } else {
+        ProgressDrawTarget::stderr()
+    }
+}
+
+/// Record an event in the log, and print it as JSON if JSON events are enabled.
+pub fn emit(event: Event) {
+    log::log!(event.level(), "{event:?}");
+    if json_events() {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs_f64();
+        if let Ok(line) = serde_json::to_string(&Line { time, event: &event }) {
+            let _lock = STDOUT_LOCK.lock().unwrap();
+            let _ = writeln!(io::stdout(), "{line}");
+        }
+    }
+}
+
+/// A logger that appends leveled, timestamped lines to a file.
+struct FileLogger {
+    level: LevelFilter,
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs_f64();
+        let mut file = self.file.lock().unwrap();
+        let _ = writeln!(file, "{time:.3} {:<5} {}: {}", record.level(), record.target(), record.args());
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock().unwrap().flush();
+    }
+}
+
+/// Append log messages at `level` and above to a file, for monitoring and auditing long runs.
+pub fn init_log_file(file: &Path, level: LevelFilter) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(file)?;
+    log::set_boxed_logger(Box::new(FileLogger { level, file: Mutex::new(file) }))
+        .map_err(io::Error::other)?;
+    log::set_max_level(level);
+    Ok(())
+}
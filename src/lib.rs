@@ -2,4 +2,7 @@ mod analyze;
 pub use analyze::*;
 
 mod reddit;
-pub use reddit::*;
\ No newline at end of file
+pub use reddit::*;
+
+mod events;
+pub use events::*;
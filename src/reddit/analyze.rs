@@ -1,16 +1,77 @@
 use crate::*;
 use serde::{Deserialize, Serialize};
-use std::{path::Path, fs::{read_to_string, write}};
+use std::{path::Path, fs::{read_to_string, write}, collections::BTreeMap, fmt, str::FromStr};
 use rayon::prelude::*;
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct SubmissionAnalysis {
+    /// The subreddit of the post this is an analysis of, if it was recorded.
+    /// Collections like search results contain posts from many subreddits, and users post in many of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subreddit: Option<String>,
     /// The analysis of the submission's content.
+    /// For a post, this is the analysis of its title together with its body.
     pub analysis: Analysis,
+    /// The analysis of a post's title on its own, if it was analyzed on its own. See `set_post_content`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<Analysis>,
+    /// The analysis of a post's body on its own, if it was analyzed on its own. Link and image posts have no body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<Analysis>,
     /// The analysis of the submission's comments.
     pub children: Vec<SubmissionAnalysis>,
 }
 
+/// Which part of a post stands for the post in statistics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PostContent {
+    /// The title and the body, analyzed together.
+    #[default]
+    Combined,
+    /// Only the title.
+    Title,
+    /// Only the body, so posts without one count as neutral.
+    Body,
+}
+
+impl FromStr for PostContent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "combined" => Ok(Self::Combined),
+            "title" => Ok(Self::Title),
+            "body" => Ok(Self::Body),
+            _ => Err(format!("unknown post content \"{s}\", expected combined, title or body")),
+        }
+    }
+}
+
+impl fmt::Display for PostContent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Combined => "combined",
+            Self::Title => "title",
+            Self::Body => "body",
+        })
+    }
+}
+
+lazy_static! {
+    static ref POST_CONTENT: std::sync::RwLock<PostContent> = std::sync::RwLock::new(PostContent::Combined);
+}
+
+/// Also analyze this part of each post on its own, besides the title and body together.
+/// Each post with a body then costs one more classification. Only the combined text is analyzed by default.
+pub fn set_post_content(content: PostContent) {
+    *POST_CONTENT.write().unwrap() = content;
+}
+
+/// Which part of each post is analyzed on its own, besides the title and body together.
+pub fn post_content() -> PostContent {
+    *POST_CONTENT.read().unwrap()
+}
+
 impl Data for SubmissionAnalysis {
     fn save(&self, file: &Path) {
         if let Ok(output_json) = serde_json::to_string(&self) {
@@ -40,6 +101,22 @@ impl Data for Vec<SubmissionAnalysis> {
 }
 
 impl SubmissionAnalysis {
+    /// The analysis of the part of a post chosen to stand for it.
+    /// A part that wasn't analyzed on its own, see `set_post_content`, falls back to the combined analysis.
+    /// Analyses made before titles were analyzed only have the body, so they fall back to it too.
+    pub fn post_analysis(&self, content: PostContent) -> Analysis {
+        match content {
+            PostContent::Combined => self.analysis.clone(),
+            PostContent::Title => self.title.clone().unwrap_or_else(|| self.analysis.clone()),
+            PostContent::Body => self.body.clone().unwrap_or_else(|| if self.title.is_some() { Analysis::default() } else { self.analysis.clone() }),
+        }
+    }
+
+    /// Make the chosen part of the post the analysis of its content.
+    pub fn use_post_content(&mut self, content: PostContent) {
+        self.analysis = self.post_analysis(content);
+    }
+
     /// How positive are the replies to this post?
     pub fn reply_positivity(&self) -> f64 {
         self.children
@@ -86,6 +163,7 @@ impl SubmissionAnalysis {
         use std::collections::BTreeMap;
         let mut subjects = BTreeMap::new();
         let mut attitudes = BTreeMap::new();
+        let (neutral, other) = (Attitude::fallback(), Subject::fallback());
 
         for reply in &self.children {
             let subject = reply.analysis.subject;
@@ -99,28 +177,31 @@ impl SubmissionAnalysis {
         Analysis {
             attitude: attitudes
                 .iter()
-                .filter(|(attitude, _)| !matches!(attitude, Attitude::Neutral))
+                .filter(|(attitude, _)| **attitude != neutral)
                 .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
                 .map(|(attitude, _)| *attitude)
-                .unwrap_or(Attitude::Neutral),
+                .unwrap_or(neutral),
             attitude_confidence: attitudes
                 .iter()
-                .filter(|(attitude, _)| !matches!(attitude, Attitude::Neutral))
+                .filter(|(attitude, _)| **attitude != neutral)
                 .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
                 .map(|(_, count)| *count / self.children.len() as f64)
                 .unwrap_or(0.0),
             subject: subjects
                 .iter()
-                .filter(|(subject, _)| !matches!(subject, Subject::Other))
+                .filter(|(subject, _)| **subject != other)
                 .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
                 .map(|(subject, _)| *subject)
-                .unwrap_or(Subject::Other),
+                .unwrap_or(other),
             subject_confidence: subjects
                 .iter()
-                .filter(|(subject, _)| !matches!(subject, Subject::Other))
+                .filter(|(subject, _)| **subject != other)
                 .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
                 .map(|(_, count)| *count / self.children.len() as f64)
                 .unwrap_or(0.0),
+            scores: None,
+            multi_label: None,
+            chunked: None,
         }
     }
 
@@ -140,9 +221,10 @@ pub trait Submission {
     }
 }
 
+/// A post's content is its title. Its body is analyzed along with it, see `AnalyzeSubmission for Post`.
 impl Submission for Post {
     fn content(&self) -> &str {
-        &self.body
+        &self.title
     }
 
     fn score(&self) -> i32 {
@@ -164,13 +246,162 @@ impl Submission for Comment {
     }
 
     fn replies(&self) -> &[Comment] {
-        &&self.comments
+        &self.comments
+    }
+}
+
+/// A user's history is treated as a submission whose replies are everything the user posted.
+impl Submission for User {
+    fn content(&self) -> &str {
+        ""
+    }
+
+    fn score(&self) -> i32 {
+        self.posts.iter().map(|post| post.score).chain(self.comments.iter().map(|comment| comment.score)).sum()
+    }
+
+    fn replies(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    fn size(&self) -> usize {
+        self.posts.iter().map(|post| post.size()).sum::<usize>() + self.comments.iter().map(|comment| comment.size()).sum::<usize>()
+    }
+}
+
+/// A post's title and body are analyzed together as the post's content,
+/// and the part chosen with `set_post_content` is also analyzed on its own.
+/// A post without a body is only analyzed once, as its title is all of its content.
+impl AnalyzeSubmission for Post {
+    fn texts(&self, texts: &mut Vec<(String, bool)>) {
+        if self.body.is_empty() {
+            texts.push((self.title.clone(), true));
+        } else {
+            let part = match post_content() {
+                PostContent::Combined => None,
+                PostContent::Title => Some(self.title.clone()),
+                PostContent::Body => Some(self.body.clone()),
+            };
+            texts.push((format!("{}\n\n{}", self.title, self.body), part.is_none()));
+            texts.extend(part.map(|part| (part, true)));
+        }
+        self.reply_texts(texts);
+    }
+
+    fn assemble(&self, analyses: &mut dyn Iterator<Item = Result<Analysis, AnalysisError>>) -> Result<SubmissionAnalysis, AnalysisError> {
+        let content = post_content();
+        let analysis = next_analysis(analyses);
+        let part = (!self.body.is_empty() && content != PostContent::Combined).then(|| next_analysis(analyses));
+        let children = self.assemble_replies(analyses);
+        let analysis = analysis?;
+        let part = part.transpose()?;
+        let (title, body) = match content {
+            _ if self.body.is_empty() => (Some(analysis.clone()), None),
+            PostContent::Combined => (None, None),
+            PostContent::Title => (part, None),
+            PostContent::Body => (None, part),
+        };
+        Ok(SubmissionAnalysis { subreddit: None, analysis, title, body, children })
     }
 }
 
-impl AnalyzeSubmission for Post {}
 impl AnalyzeSubmission for Comment {}
 
+impl AnalyzeSubmission for User {
+    /// The texts of the user's posts, followed by those of their comments. Empty comments are kept.
+    fn texts(&self, texts: &mut Vec<(String, bool)>) {
+        for post in &self.posts {
+            post.texts(texts);
+        }
+        for comment in &self.comments {
+            comment.texts(texts);
+        }
+    }
+
+    /// The children of a user's analysis are the analyses of their posts, followed by their comments,
+    /// each recording the subreddit it was posted in. Submissions that fail to be analyzed are left out.
+    fn assemble(&self, analyses: &mut dyn Iterator<Item = Result<Analysis, AnalysisError>>) -> Result<SubmissionAnalysis, AnalysisError> {
+        let posts = self.posts.iter().map(|post| (&post.subreddit, post.assemble(analyses))).collect::<Vec<_>>();
+        let comments = self.comments.iter().map(|comment| (&comment.subreddit, comment.assemble(analyses))).collect::<Vec<_>>();
+        let children = posts
+            .into_iter()
+            .chain(comments)
+            .filter_map(|(subreddit, analysis)| Some(SubmissionAnalysis { subreddit: Some(subreddit.clone()), ..analysis.ok()? }))
+            .collect();
+        Ok(SubmissionAnalysis { subreddit: None, analysis: Analysis::default(), title: None, body: None, children })
+    }
+}
+
+/// A user's attitudes and subjects within one subreddit.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SubredditProfile {
+    /// The number of posts and comments the user made in the subreddit.
+    pub submissions: usize,
+    /// How many of the user's submissions had each attitude.
+    pub attitudes: BTreeMap<Attitude, usize>,
+    /// How many of the user's submissions had each subject.
+    pub subjects: BTreeMap<Subject, usize>,
+    /// The average positivity of the user's submissions.
+    pub positivity: f64,
+    /// The average agreement of the user's submissions.
+    pub agreement: f64,
+}
+
+impl SubredditProfile {
+    /// The user's most common attitude in the subreddit, other than neutral.
+    pub fn top_attitude(&self) -> Option<(Attitude, usize)> {
+        let neutral = Attitude::fallback();
+        self.attitudes.iter().filter(|(attitude, _)| **attitude != neutral).max_by_key(|(_, count)| **count).map(|(a, c)| (*a, *c))
+    }
+
+    /// The user's most common subject in the subreddit, other than other.
+    pub fn top_subject(&self) -> Option<(Subject, usize)> {
+        let other = Subject::fallback();
+        self.subjects.iter().filter(|(subject, _)| **subject != other).max_by_key(|(_, count)| **count).map(|(s, c)| (*s, *c))
+    }
+}
+
+impl Subreddit {
+    /// The text analyzed for each post and comment, by ID. A post's text is its title and body together.
+    pub fn texts_by_id(&self) -> BTreeMap<String, String> {
+        fn add_comments(comments: &[Comment], texts: &mut BTreeMap<String, String>) {
+            for comment in comments {
+                texts.insert(comment.id.clone(), comment.body.clone());
+                add_comments(&comment.comments, texts);
+            }
+        }
+        let mut texts = BTreeMap::new();
+        for post in &self.posts {
+            let text = if post.body.is_empty() { post.title.clone() } else { format!("{}\n\n{}", post.title, post.body) };
+            texts.insert(post.id.clone(), text);
+            add_comments(&post.comments, &mut texts);
+        }
+        texts
+    }
+}
+
+impl User {
+    /// The user's attitude and subject profile in each subreddit they posted in.
+    pub fn profile(&self, analysis: &SubmissionAnalysis) -> BTreeMap<String, SubredditProfile> {
+        let mut profiles = BTreeMap::<String, SubredditProfile>::new();
+        for child in &analysis.children {
+            let Some(subreddit) = &child.subreddit else { continue };
+            let profile = profiles.entry(subreddit.clone()).or_default();
+            let analysis = &child.analysis;
+            profile.submissions += 1;
+            *profile.attitudes.entry(analysis.attitude).or_insert(0) += 1;
+            *profile.subjects.entry(analysis.subject).or_insert(0) += 1;
+            profile.positivity += analysis.attitude.positivity();
+            profile.agreement += analysis.attitude.agreement();
+        }
+        for profile in profiles.values_mut() {
+            profile.positivity /= profile.submissions as f64;
+            profile.agreement /= profile.submissions as f64;
+        }
+        profiles
+    }
+}
+
 
 use lazy_static::lazy_static;
 lazy_static! {
@@ -178,22 +409,56 @@ lazy_static! {
 }
 
 pub trait AnalyzeSubmission: Submission {
-    fn analyze_submission(&self) -> Result<SubmissionAnalysis, AnalysisError> {
-        let replies = self.replies().to_vec();
-        let children = replies
-            .into_par_iter()
+    /// Add the texts to classify for the submission and its replies, in the order `assemble` takes
+    /// their analyses. Each text is marked if it is the last one of its submission, to count progress.
+    fn texts(&self, texts: &mut Vec<(String, bool)>) {
+        texts.push((self.content().to_string(), true));
+        self.reply_texts(texts);
+    }
+
+    /// Add the texts of the replies to the submission, leaving out empty ones.
+    fn reply_texts(&self, texts: &mut Vec<(String, bool)>) {
+        for reply in self.replies().iter().filter(|reply| reply.content() != "") {
+            reply.texts(texts);
+        }
+    }
+
+    /// Build the analysis of the submission from the analyses of its texts, in the order of `texts`.
+    fn assemble(&self, analyses: &mut dyn Iterator<Item = Result<Analysis, AnalysisError>>) -> Result<SubmissionAnalysis, AnalysisError> {
+        let analysis = next_analysis(analyses);
+        let children = self.assemble_replies(analyses);
+        Ok(SubmissionAnalysis { subreddit: None, analysis: analysis?, title: None, body: None, children })
+    }
+
+    /// Build the analyses of the replies to the submission, leaving out empty ones and ones that could not be analyzed.
+    fn assemble_replies(&self, analyses: &mut dyn Iterator<Item = Result<Analysis, AnalysisError>>) -> Vec<SubmissionAnalysis> {
+        self.replies()
+            .iter()
             .filter(|reply| reply.content() != "")
-            // .into_iter()
-            .map(|reply| reply.analyze_submission())
-            .filter(|x| x.is_ok())
-            .collect::<Result<Vec<_>, _>>()?;
-        let analysis = self.content().analyze()?;
-        {
-            let mut total = ANALYZED_COMMENTS.lock().unwrap();
-            *total += 1;
-            drop(total);
-        }
-        
-        Ok(SubmissionAnalysis { analysis, children })
+            .filter_map(|reply| reply.assemble(analyses).ok())
+            .collect()
     }
+
+    /// Analyze the submission and its replies. Their texts are classified in batches of `batch_size()`,
+    /// and the batches are spread over the classifier's workers.
+    fn analyze_submission(&self) -> Result<SubmissionAnalysis, AnalysisError> {
+        let mut texts = vec![];
+        self.texts(&mut texts);
+        let analyses = texts
+            .par_chunks(batch_size())
+            .map(|batch| {
+                let analyses = analyze_batch(&batch.iter().map(|(text, _)| text.as_str()).collect::<Vec<_>>());
+                let mut total = ANALYZED_COMMENTS.lock().unwrap();
+                *total += batch.iter().filter(|(_, last)| *last).count();
+                drop(total);
+                analyses
+            })
+            .collect::<Vec<_>>();
+        self.assemble(&mut analyses.into_iter().flatten())
+    }
+}
+
+/// The analysis of the next text. `texts` and `assemble` always line up, so there is one.
+fn next_analysis(analyses: &mut dyn Iterator<Item = Result<Analysis, AnalysisError>>) -> Result<Analysis, AnalysisError> {
+    analyses.next().expect("an analysis for every text")
 }
@@ -0,0 +1,154 @@
+use crate::Attitude;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs::{read_to_string, write}, path::Path};
+use super::{synthetic::Rng, Comment, Data, Subreddit};
+
+/// A post or comment to be labeled by hand, with the submissions it replies to.
+#[derive(Clone, Debug, Default)]
+pub struct AnnotationItem {
+    /// The ID of the post or comment.
+    pub id: String,
+    /// The text that is labeled. A post's text is its title and body together, as it is analyzed.
+    pub text: String,
+    /// The texts of the post and the comments this one replies to, from the post down to its parent.
+    /// Posts have no context.
+    pub context: Vec<String>,
+}
+
+impl Subreddit {
+    /// Every post and comment with text, to be labeled by hand. Labels are matched to texts by ID,
+    /// so posts and comments collected without one are left out, though they are still shown as context.
+    pub fn annotation_items(&self) -> Vec<AnnotationItem> {
+        fn add_comments(comments: &[Comment], context: &mut Vec<String>, items: &mut Vec<AnnotationItem>) {
+            for comment in comments.iter().filter(|comment| !comment.body.is_empty()) {
+                if !comment.id.is_empty() {
+                    items.push(AnnotationItem { id: comment.id.clone(), text: comment.body.clone(), context: context.clone() });
+                }
+                context.push(comment.body.clone());
+                add_comments(&comment.comments, context, items);
+                context.pop();
+            }
+        }
+        let mut items = vec![];
+        for post in &self.posts {
+            // The same text as `texts_by_id` gives, which can't be looked up for posts without an ID.
+            let text = if post.body.is_empty() { post.title.clone() } else { format!("{}\n\n{}", post.title, post.body) };
+            if !post.id.is_empty() {
+                items.push(AnnotationItem { id: post.id.clone(), text: text.clone(), context: vec![] });
+            }
+            add_comments(&post.comments, &mut vec![text], &mut items);
+        }
+        items
+    }
+}
+
+/// The predicted attitudes the items to label are stratified by, saved next to the labels file
+/// so later sessions only classify posts and comments collected since.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AnnotationStrata {
+    /// The seed the sample is drawn with.
+    pub seed: u64,
+    /// The subreddits the items are sampled from.
+    pub subreddits: Vec<String>,
+    /// The classifier that predicted the attitudes.
+    pub classifier: String,
+    /// The predicted attitude of each item, by subreddit and then by ID.
+    pub attitudes: BTreeMap<String, BTreeMap<String, Attitude>>,
+}
+
+impl AnnotationStrata {
+    /// The file the strata of a labels file are saved to.
+    pub fn file(labels: &Path) -> std::path::PathBuf {
+        labels.with_extension("strata.json")
+    }
+
+    /// Does this hold the strata of a sample drawn with this seed, from these subreddits, with this classifier?
+    pub fn is_for(&self, seed: u64, subreddits: &[String], classifier: &str) -> bool {
+        self.seed == seed && self.subreddits == subreddits && self.classifier == classifier
+    }
+
+    /// The predicted attitude of an item, if it was classified before.
+    pub fn attitude(&self, subreddit: &str, id: &str) -> Option<Attitude> {
+        self.attitudes.get(subreddit)?.get(id).copied()
+    }
+}
+
+impl Data for AnnotationStrata {
+    fn save(&self, file: &Path) {
+        if let Ok(output_json) = serde_json::to_string(&self) {
+            write(file, output_json).unwrap();
+        }
+    }
+
+    fn restore(&mut self, file: &Path) {
+        if let Ok(input_json) = read_to_string(file) {
+            *self = serde_json::from_str(&input_json).unwrap();
+        }
+    }
+}
+
+/// The order to label items in, given the stratum of each item, such as its subreddit and predicted label.
+/// The order takes turns between the strata, so rare ones are labeled as early as common ones.
+/// Items are shuffled within their stratum, and the strata are shuffled too, so the same seed always gives the same order.
+pub fn stratified_order<K: Ord + Clone>(strata: &[K], seed: u64) -> Vec<usize> {
+    let mut rng = Rng::new(seed);
+    let mut shuffle = |items: &mut Vec<usize>| {
+        for i in (1..items.len()).rev() {
+            items.swap(i, rng.below(i + 1));
+        }
+    };
+    let mut by_stratum = BTreeMap::<K, Vec<usize>>::new();
+    for (i, stratum) in strata.iter().enumerate() {
+        by_stratum.entry(stratum.clone()).or_default().push(i);
+    }
+    let mut groups = by_stratum.into_values().collect::<Vec<_>>();
+    for group in &mut groups {
+        shuffle(group);
+    }
+    let mut group_order = (0..groups.len()).collect();
+    shuffle(&mut group_order);
+
+    let longest = groups.iter().map(|group| group.len()).max().unwrap_or(0);
+    let (groups, group_order) = (&groups, &group_order);
+    (0..longest)
+        .flat_map(|turn| group_order.iter().filter_map(move |group| groups[*group].get(turn).copied()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Post;
+
+    #[test]
+    fn items_without_an_id_are_left_out_but_kept_as_context() {
+        let comment = |id: &str, body: &str, comments| Comment { id: id.to_string(), body: body.to_string(), comments, ..Default::default() };
+        let subreddit = Subreddit {
+            posts: vec![Post {
+                title: "Old post".to_string(),
+                body: "Collected before IDs".to_string(),
+                comments: vec![comment("", "Old comment", vec![comment("c2", "New reply", vec![])])],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let items = subreddit.annotation_items();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, "c2");
+        assert_eq!(items[0].context, ["Old post\n\nCollected before IDs", "Old comment"]);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_order() {
+        let strata = ["a", "a", "a", "b", "c", "c"];
+        let order = stratified_order(&strata, 5);
+        assert_eq!(order, stratified_order(&strata, 5));
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, [0, 1, 2, 3, 4, 5]);
+        // Each stratum gets a turn before any gets a second one.
+        let mut first = order[..3].iter().map(|i| strata[*i]).collect::<Vec<_>>();
+        first.sort_unstable();
+        assert_eq!(first, ["a", "b", "c"]);
+    }
+}
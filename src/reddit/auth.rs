@@ -0,0 +1,161 @@
+use super::{RateLimiter, ScrapeError};
+use serde::Deserialize;
+use std::{env, fmt, fs::read_to_string, path::Path, sync::Mutex, time::{Duration, Instant}};
+
+const TOKEN_URL: &str = "https://www.reddit.com/api/v1/access_token";
+/// Tokens are refreshed this long before Reddit says they expire.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// The credentials of a Reddit "script" app.
+///
+/// These are read from a config file or the environment, never from the command line,
+/// so they don't end up in shell histories or process listings.
+#[derive(Clone, Deserialize)]
+pub struct Credentials {
+    /// The app's client ID.
+    pub client_id: String,
+    /// The app's client secret.
+    pub client_secret: String,
+    /// The user agent Reddit asks script apps to identify themselves with.
+    pub user_agent: String,
+    /// The account to log in as. Without it, the app is only granted read access.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// The password of the account to log in as.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Where access tokens are requested from. Defaults to Reddit's token endpoint.
+    #[serde(default)]
+    pub token_url: Option<String>,
+}
+
+impl Credentials {
+    /// The file credentials are read from when no other file is given.
+    pub const DEFAULT_FILE: &'static str = "reddit-credentials.json";
+
+    /// Read credentials from a JSON config file.
+    pub fn from_file(file: &Path) -> Result<Self, ScrapeError> {
+        let input_json = read_to_string(file).map_err(|_| ScrapeError::CouldNotRead(file.display().to_string()))?;
+        Ok(serde_json::from_str(&input_json)?)
+    }
+
+    /// Read credentials from the `REDDIT_CLIENT_ID`, `REDDIT_CLIENT_SECRET`, `REDDIT_USER_AGENT`,
+    /// `REDDIT_USERNAME` and `REDDIT_PASSWORD` environment variables.
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            client_id: env::var("REDDIT_CLIENT_ID").ok()?,
+            client_secret: env::var("REDDIT_CLIENT_SECRET").ok()?,
+            user_agent: env::var("REDDIT_USER_AGENT").ok()?,
+            username: env::var("REDDIT_USERNAME").ok(),
+            password: env::var("REDDIT_PASSWORD").ok(),
+            token_url: env::var("REDDIT_TOKEN_URL").ok(),
+        })
+    }
+
+    /// Read credentials from the given file, or else from the environment,
+    /// or else from the default file if it exists.
+    pub fn load(file: Option<&Path>) -> Result<Option<Self>, ScrapeError> {
+        if let Some(file) = file {
+            return Self::from_file(file).map(Some);
+        }
+        if let Some(credentials) = Self::from_env() {
+            return Ok(Some(credentials));
+        }
+        let default_file = Path::new(Self::DEFAULT_FILE);
+        if default_file.exists() {
+            return Self::from_file(default_file).map(Some);
+        }
+        Ok(None)
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"<redacted>")
+            .field("user_agent", &self.user_agent)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("token_url", &self.token_url)
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    expires_in: Option<u64>,
+    error: Option<String>,
+}
+
+struct Token {
+    access_token: String,
+    expires_at: Instant,
+}
+
+/// Requests access tokens for a set of credentials, and refreshes them before they expire.
+pub(crate) struct Authenticator {
+    credentials: Credentials,
+    token: Mutex<Option<Token>>,
+}
+
+impl Authenticator {
+    pub(crate) fn new(credentials: Credentials) -> Self {
+        Self { credentials, token: Mutex::new(None) }
+    }
+
+    pub(crate) fn user_agent(&self) -> &str {
+        &self.credentials.user_agent
+    }
+
+    /// A valid access token, requesting a new one if the current one is missing or about to expire.
+    /// Token requests count against the rate limit like any other request.
+    pub(crate) fn token(&self, http: &reqwest::blocking::Client, limiter: &RateLimiter) -> Result<String, ScrapeError> {
+        if let Some(token) = self.token.lock().unwrap().as_ref() {
+            if token.expires_at > Instant::now() + REFRESH_MARGIN {
+                return Ok(token.access_token.clone());
+            }
+        }
+
+        // The lock is not held while the token is requested, so other requests aren't stuck behind a slow
+        // token endpoint. Requests that find the token expired at the same time each request a new one.
+        limiter.acquire();
+
+        let credentials = &self.credentials;
+        let form = match (&credentials.username, &credentials.password) {
+            (Some(username), Some(password)) => vec![
+                ("grant_type", "password"),
+                ("username", username.as_str()),
+                ("password", password.as_str()),
+            ],
+            _ => vec![("grant_type", "client_credentials")],
+        };
+        let response: TokenResponse = http
+            .post(credentials.token_url.as_deref().unwrap_or(TOKEN_URL))
+            .header(reqwest::header::USER_AGENT, &credentials.user_agent)
+            .basic_auth(&credentials.client_id, Some(&credentials.client_secret))
+            .form(&form)
+            .send()?
+            .error_for_status()
+            .map_err(|err| ScrapeError::Auth(err.to_string()))?
+            .json()?;
+
+        // Reddit reports bad credentials with a 200 response and an `error` field.
+        let access_token = match (response.access_token, response.error) {
+            (Some(access_token), None) => access_token,
+            (_, Some(error)) => return Err(ScrapeError::Auth(error)),
+            (None, None) => return Err(ScrapeError::Auth("no access token in response".to_string())),
+        };
+        *self.token.lock().unwrap() = Some(Token {
+            access_token: access_token.clone(),
+            expires_at: Instant::now() + Duration::from_secs(response.expires_in.unwrap_or(3600)),
+        });
+        Ok(access_token)
+    }
+
+    /// Forget the current token, after the API rejected it.
+    pub(crate) fn invalidate(&self) {
+        *self.token.lock().unwrap() = None;
+    }
+}
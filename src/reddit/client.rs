@@ -0,0 +1,366 @@
+use super::{ratelimit::retry_after, Authenticator, Credentials, RateLimiter, ScrapeError, TimeRange};
+use lazy_static::lazy_static;
+use reqwest::{blocking::Response, header::HeaderMap, StatusCode};
+use roux::{comment::CommentData, submission::SubmissionData};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{fs::{create_dir_all, read_to_string, write}, path::PathBuf, sync::{Arc, RwLock}};
+
+const BASE_URL: &str = "https://www.reddit.com";
+const OAUTH_BASE_URL: &str = "https://oauth.reddit.com";
+const USER_AGENT: &str = concat!("reddit-analyzer/", env!("CARGO_PKG_VERSION"));
+/// How many times a request is attempted before its error is returned.
+const MAX_ATTEMPTS: u32 = 5;
+
+/// A thing returned by the Reddit API, such as a listing, post or comment.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Thing<T> {
+    pub data: T,
+}
+
+/// A page of things returned by the Reddit API.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Listing<T> {
+    pub children: Vec<Thing<T>>,
+    #[serde(default)]
+    pub after: Option<String>,
+}
+
+/// The body of an error response, which says why a subreddit is unavailable.
+#[derive(Clone, Debug, Deserialize)]
+struct ErrorBody {
+    reason: Option<String>,
+}
+
+/// The most things Reddit returns in one page of a listing.
+const PAGE_SIZE: u32 = 100;
+
+/// The information returned by a subreddit's `about` endpoint.
+#[derive(Clone, Debug, Deserialize)]
+pub struct About {
+    pub public_description: Option<String>,
+    /// The subreddit's sidebar, in markdown.
+    #[serde(default)]
+    pub description: Option<String>,
+    pub subscribers: Option<u64>,
+}
+
+pub type Posts = Thing<Listing<SubmissionData>>;
+pub type Comments = Thing<Listing<CommentData>>;
+
+lazy_static! {
+    static ref SHARED_CLIENT: RwLock<Client> = RwLock::new(Client::default());
+}
+
+/// Whether raw API responses are saved to, or served from, a fixtures directory.
+#[derive(Clone, Debug, Default)]
+pub enum Fixtures {
+    /// Only talk to the API.
+    #[default]
+    Off,
+    /// Talk to the API, and save every response to the directory.
+    Record(PathBuf),
+    /// Never talk to the API, and serve every response from the directory.
+    Replay(PathBuf),
+}
+
+/// A blocking Reddit API client. Every clone of a client shares the same rate limiter.
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::blocking::Client,
+    limiter: Arc<RateLimiter>,
+    base_url: String,
+    fixtures: Fixtures,
+    auth: Option<Arc<Authenticator>>,
+}
+
+impl Client {
+    pub fn new(limiter: RateLimiter) -> Self {
+        Self {
+            http: reqwest::blocking::Client::builder()
+                .user_agent(USER_AGENT)
+                .build()
+                .expect("failed to build HTTP client"),
+            limiter: Arc::new(limiter),
+            base_url: BASE_URL.to_string(),
+            fixtures: Fixtures::Off,
+            auth: None,
+        }
+    }
+
+    /// Create a client that authenticates as a script app. Authenticated clients
+    /// are allowed 100 requests per minute, and send requests to `oauth.reddit.com`.
+    pub fn authenticated(credentials: Credentials) -> Self {
+        Self::new(RateLimiter::new(10, 100.0)).with_credentials(credentials)
+    }
+
+    /// Authenticate every request with an access token for these credentials.
+    /// The token is refreshed automatically when it expires.
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.auth = Some(Arc::new(Authenticator::new(credentials)));
+        if self.base_url == BASE_URL {
+            self.base_url = OAUTH_BASE_URL.to_string();
+        }
+        self
+    }
+
+    /// Is this client authenticated?
+    pub fn is_authenticated(&self) -> bool {
+        self.auth.is_some()
+    }
+
+    /// Send requests to a different server, such as a local stand-in for reddit.com.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Record responses to, or replay responses from, a fixtures directory.
+    pub fn with_fixtures(mut self, fixtures: Fixtures) -> Self {
+        self.fixtures = fixtures;
+        self
+    }
+
+    /// The client shared by all the `Scrape` implementations.
+    pub fn shared() -> Self {
+        SHARED_CLIENT.read().unwrap().clone()
+    }
+
+    /// Replace the client shared by all the `Scrape` implementations.
+    pub fn set_shared(client: Self) {
+        *SHARED_CLIENT.write().unwrap() = client;
+    }
+
+    /// The server that requests are sent to.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The rate limiter used by this client.
+    pub fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    /// Get a JSON document from the API, waiting for the rate limiter first.
+    /// Network errors, 429s and 5xx responses are retried with exponential backoff.
+    pub fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T, ScrapeError> {
+        if let Fixtures::Replay(dir) = &self.fixtures {
+            let file = dir.join(fixture_name(path, query));
+            let text = read_to_string(&file).map_err(|_| ScrapeError::CouldNotRead(file.display().to_string()))?;
+            return Ok(serde_json::from_str(&text)?);
+        }
+
+        let mut attempt = 1;
+        loop {
+            match self.try_get(path, query) {
+                Err(err) if err.is_retryable() && attempt < MAX_ATTEMPTS => {
+                    log::warn!("{path}: attempt {attempt}/{MAX_ATTEMPTS} failed, retrying: {err}");
+                    // 429s and 5xx responses have already backed off the limiter.
+                    if let ScrapeError::Network(_) = err {
+                        self.limiter.back_off(None);
+                    }
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn try_get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T, ScrapeError> {
+        self.limiter.acquire();
+        let mut request = self.http
+            .get(format!("{}{path}", self.base_url))
+            .query(&[("raw_json", "1")])
+            .query(query);
+        if let Some(auth) = &self.auth {
+            request = request
+                .header(reqwest::header::USER_AGENT, auth.user_agent())
+                .bearer_auth(auth.token(&self.http, &self.limiter)?);
+        }
+        let response = request.send()?;
+        self.observe(&response);
+
+        // Reddit redirects requests for subreddits that don't exist to its search page.
+        if response.url().path().starts_with("/subreddits/search") {
+            return Err(ScrapeError::NotFound(resource(path)));
+        }
+        let status = response.status();
+        if status == StatusCode::UNAUTHORIZED {
+            if let Some(auth) = &self.auth {
+                auth.invalidate();
+                return Err(ScrapeError::Unauthorized);
+            }
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = header_value(response.headers(), "retry-after").and_then(retry_after);
+            return Err(ScrapeError::RateLimited { retry_after });
+        }
+        if status == StatusCode::FORBIDDEN || status == StatusCode::NOT_FOUND {
+            let url = response.url().to_string();
+            let reason = response.json::<ErrorBody>().ok().and_then(|body| body.reason);
+            return Err(match reason.as_deref() {
+                Some("banned") => ScrapeError::Banned(resource(path)),
+                Some("private" | "quarantined" | "gold_only") => ScrapeError::Private(resource(path)),
+                _ if status == StatusCode::NOT_FOUND => ScrapeError::NotFound(resource(path)),
+                _ => ScrapeError::Status { status: status.as_u16(), url },
+            });
+        }
+        let text = response.error_for_status()?.text()?;
+        if let Fixtures::Record(dir) = &self.fixtures {
+            create_dir_all(dir)?;
+            write(dir.join(fixture_name(path, query)), &text)?;
+        }
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Update the rate limiter with the headers and status of a response.
+    fn observe(&self, response: &Response) {
+        let headers = response.headers();
+        self.limiter.observe(
+            header_value(headers, "x-ratelimit-remaining"),
+            header_value(headers, "x-ratelimit-reset"),
+        );
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            self.limiter.back_off(header_value(headers, "retry-after").and_then(retry_after));
+        } else if status.is_success() {
+            self.limiter.succeed();
+        }
+    }
+
+    /// Get the hot posts on a subreddit.
+    pub fn hot(&self, subreddit: &str, limit: u32) -> Result<Posts, ScrapeError> {
+        self.get(&format!("/r/{subreddit}/hot.json"), &[("limit", limit.to_string())])
+    }
+
+    /// Get the newest posts on a subreddit.
+    pub fn new_posts<T: DeserializeOwned>(&self, subreddit: &str, limit: u32) -> Result<Vec<T>, ScrapeError> {
+        self.listing(&format!("/r/{subreddit}/new.json"), &[], limit)
+    }
+
+    /// Get the comments on a post.
+    pub fn article_comments(&self, subreddit: &str, article: &str, depth: Option<u32>, limit: Option<u32>) -> Result<Comments, ScrapeError> {
+        let mut query = vec![];
+        if let Some(depth) = depth {
+            query.push(("depth", depth.to_string()));
+        }
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        // The first listing is the post itself, the second is its comments.
+        let (_, comments): (Posts, Comments) = self.get(&format!("/r/{subreddit}/comments/{article}.json"), &query)?;
+        Ok(comments)
+    }
+
+    /// Get a post and its comments by the post's ID, without knowing its subreddit.
+    pub fn thread<T: DeserializeOwned>(&self, article: &str, limit: Option<u32>) -> Result<(T, Comments), ScrapeError> {
+        let query = limit.map(|limit| ("limit", limit.to_string())).into_iter().collect::<Vec<_>>();
+        let (post, comments): (Thing<Listing<T>>, Comments) = self.get(&format!("/comments/{article}.json"), &query)?;
+        match post.data.children.into_iter().next() {
+            Some(post) => Ok((post.data, comments)),
+            None => Err(ScrapeError::NotFound(format!("post {article}"))),
+        }
+    }
+
+    /// Get up to `limit` things from a listing, following its pages.
+    pub fn listing<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)], limit: u32) -> Result<Vec<T>, ScrapeError> {
+        let mut things = vec![];
+        let mut after = None;
+        while (things.len() as u32) < limit {
+            let mut page_query = query.to_vec();
+            page_query.push(("limit", PAGE_SIZE.min(limit - things.len() as u32).to_string()));
+            if let Some(after) = after {
+                page_query.push(("after", after));
+            }
+            let page: Thing<Listing<T>> = self.get(path, &page_query)?;
+            let page_len = page.data.children.len();
+            things.extend(page.data.children.into_iter().map(|thing| thing.data));
+            after = page.data.after;
+            if after.is_none() || page_len == 0 {
+                break;
+            }
+        }
+        Ok(things)
+    }
+
+    /// Search for posts, optionally restricted to some subreddits.
+    pub fn search<T: DeserializeOwned>(&self, query: &str, subreddits: &[String], time: TimeRange, limit: u32) -> Result<Vec<T>, ScrapeError> {
+        let mut params = vec![
+            ("q", query.to_string()),
+            ("t", time.as_param().to_string()),
+            ("sort", "relevance".to_string()),
+        ];
+        let path = if subreddits.is_empty() {
+            "/search.json".to_string()
+        } else {
+            params.push(("restrict_sr", "1".to_string()));
+            format!("/r/{}/search.json", subreddits.join("+"))
+        };
+        self.listing(&path, &params, limit)
+    }
+
+    /// Get the posts a user has submitted, newest first.
+    pub fn user_submitted(&self, user: &str, limit: u32) -> Result<Vec<SubmissionData>, ScrapeError> {
+        self.listing(&format!("/user/{user}/submitted.json"), &[("sort", "new".to_string())], limit)
+    }
+
+    /// Get the comments a user has posted, newest first.
+    pub fn user_comments(&self, user: &str, limit: u32) -> Result<Vec<CommentData>, ScrapeError> {
+        self.listing(&format!("/user/{user}/comments.json"), &[("sort", "new".to_string())], limit)
+    }
+
+    /// Get the description and subscriber count of a subreddit.
+    pub fn about(&self, subreddit: &str) -> Result<About, ScrapeError> {
+        let about: Thing<About> = self.get(&format!("/r/{subreddit}/about.json"), &[])?;
+        Ok(about.data)
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new(RateLimiter::default())
+    }
+}
+
+/// The file a response is recorded to, such as `r_rust_hot@limit=100.json` for `/r/rust/hot.json?limit=100`.
+fn fixture_name(path: &str, query: &[(&str, String)]) -> String {
+    let path = path.trim_start_matches('/');
+    let mut name = path.strip_suffix(".json").unwrap_or(path).replace('/', "_");
+    if !query.is_empty() {
+        let mut query = query.iter().map(|(key, value)| format!("{key}={value}")).collect::<Vec<_>>();
+        query.sort();
+        name = format!("{name}@{}", query.join("&"));
+    }
+    name.retain(|c| c.is_ascii_alphanumeric() || "_-.@=&".contains(c));
+    format!("{name}.json")
+}
+
+/// The thing requested by an API path, such as `r/rust` for `/r/rust/hot.json`.
+fn resource(path: &str) -> String {
+    let mut segments = path.trim_start_matches('/').split('/');
+    match (segments.next(), segments.next()) {
+        (Some("r"), Some(name)) => format!("r/{name}"),
+        (Some("user"), Some(name)) => format!("u/{name}"),
+        _ => path.to_string(),
+    }
+}
+
+/// A numeric header. Values that are negative or not finite, like `inf`, are left out.
+fn header_value(headers: &HeaderMap, name: &str) -> Option<f64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok().filter(|value: &f64| value.is_finite() && *value >= 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_are_named_after_the_path_and_query() {
+        assert_eq!(fixture_name("/r/rust/hot.json", &[("limit", "100".to_string())]), "r_rust_hot@limit=100.json");
+        assert_eq!(fixture_name("/r/rust/about.json", &[]), "r_rust_about.json");
+        assert_eq!(
+            fixture_name("/search.json", &[("t", "all".to_string()), ("q", "rust lang".to_string())]),
+            "search@q=rustlang&t=all.json",
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::events::{self, Event};
+use super::{canonical_name, Comment, Data, ScrapeError, Scraper, Subreddit};
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, BTreeSet, VecDeque}, fs::{read_to_string, write}, path::Path};
+
+/// Where a link from one subreddit to another was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum LinkSource {
+    /// The subreddit's sidebar or description links to the other subreddit.
+    Sidebar,
+    /// A post or comment on the subreddit mentions the other subreddit.
+    Mention,
+    /// A post on the subreddit was crossposted from the other subreddit.
+    Crosspost,
+}
+
+/// A subreddit discovered by a crawl.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommunityNode {
+    /// The subreddit's canonical name, which its dataset is saved under.
+    pub name: String,
+    /// How many links away from a seed the subreddit was discovered.
+    pub depth: usize,
+    /// The number of subscribers, if the subreddit was collected.
+    pub subscribers: u64,
+    /// Was the subreddit's data collected?
+    pub collected: bool,
+}
+
+/// The links found from one subreddit to another.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommunityEdge {
+    pub from: String,
+    pub to: String,
+    pub source: LinkSource,
+    /// How many times the link was found.
+    pub count: usize,
+}
+
+/// The subreddits discovered by a crawl, and the links between them.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CommunityGraph {
+    /// The subreddits the crawl started from.
+    pub seeds: Vec<String>,
+    pub nodes: Vec<CommunityNode>,
+    pub edges: Vec<CommunityEdge>,
+}
+
+impl Data for CommunityGraph {
+    fn save(&self, file: &Path) {
+        if let Ok(output_json) = serde_json::to_string(&self) {
+            write(file, output_json).unwrap();
+        }
+    }
+
+    fn restore(&mut self, file: &Path) {
+        if let Ok(input_json) = read_to_string(file) {
+            *self = serde_json::from_str(&input_json).unwrap();
+        }
+    }
+}
+
+/// A breadth-first crawl of the subreddits related to some seed subreddits.
+#[derive(Clone, Debug)]
+pub struct Crawl {
+    /// The subreddits to start from.
+    pub seeds: Vec<String>,
+    /// How many links away from the seeds to follow. The seeds are at depth 0.
+    pub max_depth: usize,
+    /// The most subreddits to collect, including the seeds. Subreddits that fail to be collected don't count.
+    pub max_subreddits: usize,
+}
+
+impl Crawl {
+    /// Crawl breadth-first from the seeds, collecting every subreddit that is reached with the scraper
+    /// and storing it in the scraper's sink. Subreddits the sink already has are read from it instead.
+    /// Subreddits that are linked to but not reached are in the graph as nodes that were not collected.
+    /// `on_visit` is called with each subreddit as it is visited, and with its error if it couldn't be collected.
+    pub fn run(&self, scraper: &Scraper, mut on_visit: impl FnMut(&str, usize, Option<&ScrapeError>)) -> CommunityGraph {
+        let mut queue = VecDeque::new();
+        let mut discovered = BTreeSet::new();
+        for seed in &self.seeds {
+            if discovered.insert(canonical_name(seed)) {
+                queue.push_back((canonical_name(seed), 0));
+            }
+        }
+
+        let mut nodes = vec![];
+        let mut edges = BTreeMap::<(String, String, LinkSource), usize>::new();
+        // The depth each subreddit was first linked to from, for the ones that are never visited.
+        let mut linked = BTreeMap::<String, usize>::new();
+        let mut collected = 0;
+        while let Some((name, depth)) = queue.pop_front() {
+            if collected >= self.max_subreddits {
+                break;
+            }
+
+            let links = match self.visit(scraper, &name) {
+                Ok((subreddit, links)) => {
+                    on_visit(&name, depth, None);
+                    collected += 1;
+                    nodes.push(CommunityNode { name: name.clone(), depth, subscribers: subreddit.subscribers, collected: true });
+                    links
+                }
+                Err(err) => {
+                    on_visit(&name, depth, Some(&err));
+                    nodes.push(CommunityNode { name: name.clone(), depth, subscribers: 0, collected: false });
+                    continue;
+                }
+            };
+
+            for (to, source) in links {
+                if to == name {
+                    continue;
+                }
+                *edges.entry((name.clone(), to.clone(), source)).or_insert(0) += 1;
+                linked.entry(to.clone()).or_insert(depth + 1);
+                if depth < self.max_depth && discovered.insert(to.clone()) {
+                    queue.push_back((to, depth + 1));
+                }
+            }
+        }
+
+        for (name, depth) in linked {
+            if !nodes.iter().any(|node| node.name == name) {
+                nodes.push(CommunityNode { name, depth, subscribers: 0, collected: false });
+            }
+        }
+
+        CommunityGraph {
+            seeds: self.seeds.clone(),
+            nodes,
+            edges: edges
+                .into_iter()
+                .map(|((from, to, source), count)| CommunityEdge { from, to, source, count })
+                .collect(),
+        }
+    }
+
+    /// Collect a subreddit, or read it if the sink already has it, and find the subreddits it links to.
+    fn visit(&self, scraper: &Scraper, name: &str) -> Result<(Subreddit, Vec<(String, LinkSource)>), ScrapeError> {
+        let mut links = vec![];
+        let about = scraper.client().about(name).inspect_err(|err| {
+            events::emit(Event::Error { subreddit: name.to_string(), id: None, error: err.to_string() });
+        })?;
+        for text in [&about.description, &about.public_description].into_iter().flatten() {
+            links.extend(subreddit_mentions(text).into_iter().map(|to| (to, LinkSource::Sidebar)));
+        }
+
+        let subreddit = match scraper.sink().get(name) {
+            Some(subreddit) => subreddit,
+            None => scraper.reporting_waits(|| scraper.collect_into_sink(0, name, || scraper.collect(0, name)))?,
+        };
+
+        for post in &subreddit.posts {
+            if let Some(origin) = &post.crosspost_from {
+                links.push((origin.clone(), LinkSource::Crosspost));
+            }
+            for text in [&post.title, &post.body] {
+                links.extend(subreddit_mentions(text).into_iter().map(|to| (to, LinkSource::Mention)));
+            }
+            comment_mentions(&post.comments, &mut links);
+        }
+        Ok((subreddit, links))
+    }
+}
+
+fn comment_mentions(comments: &[Comment], links: &mut Vec<(String, LinkSource)>) {
+    for comment in comments {
+        links.extend(subreddit_mentions(&comment.body).into_iter().map(|to| (to, LinkSource::Mention)));
+        comment_mentions(&comment.comments, links);
+    }
+}
+
+/// Find the subreddits mentioned in a text as `r/name` or `/r/name`, in lowercase.
+pub fn subreddit_mentions(text: &str) -> Vec<String> {
+    let mut mentions = vec![];
+    let bytes = text.as_bytes();
+    let mut start = 0;
+    while let Some(offset) = text[start..].find("r/") {
+        let i = start + offset;
+        start = i + 2;
+        // The mention must start a word, or follow a slash as in "reddit.com/r/name".
+        if i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_') {
+            continue;
+        }
+        let name = text[i + 2..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect::<String>();
+        // Subreddit names are between 2 and 21 characters long.
+        if (2..=21).contains(&name.len()) {
+            mentions.push(canonical_name(&name));
+        }
+    }
+    mentions
+}
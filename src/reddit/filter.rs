@@ -0,0 +1,154 @@
+use super::{Comment, Thing};
+use lazy_static::lazy_static;
+use roux::{comment::CommentData, reply::MaybeReplies, submission::SubmissionData};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+lazy_static! {
+    static ref SHARED_FILTERS: RwLock<Filters> = RwLock::new(Filters::default());
+}
+
+/// Why a post or comment was left out of a dataset.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilterReason {
+    /// The post is marked not safe for work.
+    NotSafeForWork,
+    /// The post or comment was stickied by the moderators.
+    Stickied,
+    /// The post or comment was removed by the moderators.
+    Removed,
+    /// The post or comment was deleted by its author.
+    Deleted,
+    /// The post or comment was made by a known bot.
+    Bot(String),
+    /// The post or comment's score was below the minimum.
+    LowScore(i32),
+    /// The post had fewer comments than the minimum.
+    FewComments(u64),
+}
+
+/// A post or comment that was left out of a dataset, and why.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Filtered {
+    /// The ID of the post or comment.
+    pub id: String,
+    pub reason: FilterReason,
+}
+
+/// Which posts and comments to leave out of a dataset at collection time.
+/// A comment that is left out is left out with all of its replies.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Filters {
+    /// Leave out posts marked not safe for work.
+    pub exclude_nsfw: bool,
+    /// Leave out stickied posts and comments, such as moderator announcements.
+    pub exclude_stickied: bool,
+    /// Leave out posts and comments whose body was removed or deleted.
+    pub exclude_removed: bool,
+    /// Leave out posts and comments by these accounts, such as "AutoModerator".
+    pub bots: Vec<String>,
+    /// Leave out posts and comments with a lower score.
+    pub min_score: Option<i32>,
+    /// Leave out posts with fewer comments.
+    pub min_comments: Option<u64>,
+}
+
+impl Filters {
+    /// Bots that post on many subreddits.
+    pub const KNOWN_BOTS: [&'static str; 4] = ["AutoModerator", "RemindMeBot", "sneakpeekbot", "RepostSleuthBot"];
+
+    /// The filters used by all the `Scrape` implementations.
+    pub fn shared() -> Self {
+        SHARED_FILTERS.read().unwrap().clone()
+    }
+
+    /// Replace the filters used by all the `Scrape` implementations.
+    pub fn set_shared(filters: Self) {
+        *SHARED_FILTERS.write().unwrap() = filters;
+    }
+
+    fn removed_reason(body: &str) -> Option<FilterReason> {
+        match body.trim() {
+            "[removed]" => Some(FilterReason::Removed),
+            "[deleted]" => Some(FilterReason::Deleted),
+            _ => None,
+        }
+    }
+
+    fn is_bot(&self, author: &str) -> bool {
+        self.bots.iter().any(|bot| bot.eq_ignore_ascii_case(author))
+    }
+
+    /// Why a post should be left out, if it should.
+    pub fn post_reason(&self, post: &SubmissionData) -> Option<FilterReason> {
+        if self.exclude_nsfw && post.over_18 {
+            return Some(FilterReason::NotSafeForWork);
+        }
+        if self.exclude_stickied && post.stickied {
+            return Some(FilterReason::Stickied);
+        }
+        if self.exclude_removed {
+            if let Some(reason) = Self::removed_reason(&post.selftext) {
+                return Some(reason);
+            }
+        }
+        if self.is_bot(&post.author) {
+            return Some(FilterReason::Bot(post.author.clone()));
+        }
+        match (self.min_score, self.min_comments) {
+            (Some(min_score), _) if (post.score as i32) < min_score => Some(FilterReason::LowScore(post.score as i32)),
+            (_, Some(min_comments)) if post.num_comments < min_comments => Some(FilterReason::FewComments(post.num_comments)),
+            _ => None,
+        }
+    }
+
+    /// Why a comment should be left out, if it should.
+    pub fn comment_reason(&self, comment: &CommentData) -> Option<FilterReason> {
+        if self.exclude_stickied && comment.stickied.unwrap_or(false) {
+            return Some(FilterReason::Stickied);
+        }
+        if self.exclude_removed {
+            if let Some(reason) = comment.body.as_deref().and_then(Self::removed_reason) {
+                return Some(reason);
+            }
+        }
+        if let Some(author) = comment.author.as_deref().filter(|author| self.is_bot(author)) {
+            return Some(FilterReason::Bot(author.to_string()));
+        }
+        match (self.min_score, comment.score) {
+            (Some(min_score), Some(score)) if score < min_score => Some(FilterReason::LowScore(score)),
+            _ => None,
+        }
+    }
+
+    /// Build a comment tree from the API's comments, leaving out the comments that should be,
+    /// and recording them in `filtered`.
+    pub fn comments(&self, raw_comments: Vec<Thing<CommentData>>, filtered: &mut Vec<Filtered>) -> Vec<Comment> {
+        let mut comments = vec![];
+        for raw_comment in raw_comments {
+            comments.extend(self.comment(raw_comment.data, filtered));
+        }
+        comments
+    }
+
+    fn comment(&self, comment: CommentData, filtered: &mut Vec<Filtered>) -> Option<Comment> {
+        if let Some(reason) = self.comment_reason(&comment) {
+            filtered.push(Filtered { id: comment.id.unwrap_or_default(), reason });
+            return None;
+        }
+
+        let mut replies = vec![];
+        if let Some(MaybeReplies::Reply(raw_replies)) = comment.replies {
+            for reply in raw_replies.data.children {
+                replies.extend(self.comment(reply.data, filtered));
+            }
+        }
+        Some(Comment {
+            id: comment.id.unwrap_or_default(),
+            subreddit: comment.subreddit.unwrap_or_default(),
+            body: comment.body.unwrap_or_default(),
+            score: comment.score.unwrap_or(0),
+            comments: replies,
+        })
+    }
+}
@@ -1,6 +1,42 @@
 mod scrape;
 pub use scrape::*;
 
+mod scraper;
+pub use scraper::*;
+
+mod client;
+pub use client::*;
+
+mod ratelimit;
+pub use ratelimit::*;
+
+mod auth;
+pub use auth::*;
+
+mod thread;
+pub use thread::*;
+
+mod user;
+pub use user::*;
+
+mod search;
+pub use search::*;
+
+mod watch;
+pub use watch::*;
+
+mod crawl;
+pub use crawl::*;
+
+mod filter;
+pub use filter::*;
+
+mod synthetic;
+pub use synthetic::*;
+
+mod annotate;
+pub use annotate::*;
+
 mod analyze;
 pub use analyze::*;
 
@@ -22,7 +58,13 @@ pub struct Subreddit {
     /// The number of subscribers to the subreddit.
     pub subscribers: u64,
     /// The posts on the subreddit.
-    pub posts: Vec<Post>
+    pub posts: Vec<Post>,
+    /// The posts that could not be collected.
+    #[serde(default)]
+    pub failed_posts: Vec<FailedPost>,
+    /// The posts that were left out by the collection filters, and why.
+    #[serde(default)]
+    pub filtered: Vec<Filtered>,
 }
 
 impl Data for Subreddit {
@@ -39,8 +81,28 @@ impl Data for Subreddit {
     }
 }
 
+/// A post that was listed on a subreddit, but could not be collected.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FailedPost {
+    /// The post's ID.
+    pub id: String,
+    /// The title of the post.
+    pub title: String,
+    /// Why the post could not be collected.
+    pub error: String,
+}
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Post {
+    /// The post's base36 ID, such as "yv7kdi".
+    #[serde(default)]
+    pub id: String,
+    /// When the post was submitted, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub created_utc: f64,
+    /// The subreddit the post was submitted to, such as "rust".
+    #[serde(default)]
+    pub subreddit: String,
     /// The title of the post.
     pub title: String,
     /// Is this post not safe for work?
@@ -52,7 +114,13 @@ pub struct Post {
     /// The score (upvotes - downvotes) the post has received.
     pub score: i32,
     /// The comments on the post.
-    pub comments: Vec<Comment>
+    pub comments: Vec<Comment>,
+    /// The comments that were left out by the collection filters, and why.
+    #[serde(default)]
+    pub filtered: Vec<Filtered>,
+    /// The subreddit the post was crossposted from, if it is a crosspost.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crosspost_from: Option<String>,
 }
 
 impl Data for Post {
@@ -72,6 +140,12 @@ impl Data for Post {
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Comment {
+    /// The comment's base36 ID.
+    #[serde(default)]
+    pub id: String,
+    /// The subreddit the comment was posted in, such as "rust".
+    #[serde(default)]
+    pub subreddit: String,
     /// The text of the comment.
     pub body: String,
     /// The score (upvotes - downvotes) the comment has received.
@@ -0,0 +1,242 @@
+use std::{sync::{atomic::{AtomicU64, Ordering}, Mutex}, thread::sleep, time::{Duration, Instant}};
+
+/// How often a waiting request wakes up to report its remaining wait time.
+const WAIT_TICK: Duration = Duration::from_millis(100);
+/// The first backoff applied after a 429 or 5xx response.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+/// The longest backoff applied after repeated 429 or 5xx responses.
+const MAX_BACKOFF: Duration = Duration::from_secs(120);
+/// The longest a rate limit window is waited out for. Reddit's windows are ten minutes long.
+const MAX_RESET: Duration = Duration::from_secs(600);
+/// The slowest the refill rate is set or adapted to, in requests per second, so a request never waits forever.
+const MIN_RATE: f64 = 1.0 / 60.0;
+
+/// A callback told how long a request is waiting for the limiter.
+type WaitObserver = Box<dyn Fn(Duration) + Send + Sync>;
+
+/// Identifies a wait callback registered with `RateLimiter::on_wait`, to remove it again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ObserverId(u64);
+
+/// A wait the server asked for, in seconds, at most `cap`. Waits that are negative or not finite are ignored.
+fn server_wait(secs: f64, cap: Duration) -> Option<Duration> {
+    if !secs.is_finite() {
+        return None;
+    }
+    Duration::try_from_secs_f64(secs.min(cap.as_secs_f64())).ok()
+}
+
+/// The wait a `Retry-After` header asks for, at most the longest backoff.
+pub(super) fn retry_after(secs: f64) -> Option<Duration> {
+    server_wait(secs, MAX_BACKOFF)
+}
+
+/// A token bucket shared by every request made through a `Client`.
+///
+/// The bucket starts with a fixed capacity and refill rate, and adapts the
+/// refill rate to Reddit's `X-Ratelimit-Remaining` and `X-Ratelimit-Reset`
+/// headers whenever a response carries them.
+pub struct RateLimiter {
+    /// The most requests that can be made in a burst.
+    capacity: f64,
+    /// The configured number of requests refilled per second.
+    default_rate: f64,
+    state: Mutex<Bucket>,
+    observers: Mutex<Vec<(ObserverId, WaitObserver)>>,
+    next_observer: AtomicU64,
+}
+
+struct Bucket {
+    tokens: f64,
+    rate: f64,
+    last_refill: Instant,
+    blocked_until: Option<Instant>,
+    backoff: Duration,
+}
+
+impl Bucket {
+    fn refill(&mut self, capacity: f64, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(capacity);
+        self.last_refill = now;
+    }
+}
+
+impl RateLimiter {
+    /// Create a rate limiter that allows `capacity` requests in a burst,
+    /// refilled at `requests_per_minute`. Rates below one request a minute, including 0, are raised to it.
+    pub fn new(capacity: u32, requests_per_minute: f64) -> Self {
+        let rate = if requests_per_minute > 0.0 { (requests_per_minute / 60.0).clamp(MIN_RATE, f64::MAX) } else { MIN_RATE };
+        Self {
+            capacity: capacity as f64,
+            default_rate: rate,
+            state: Mutex::new(Bucket {
+                tokens: capacity as f64,
+                rate,
+                last_refill: Instant::now(),
+                blocked_until: None,
+                backoff: Duration::ZERO,
+            }),
+            observers: Mutex::new(vec![]),
+            next_observer: AtomicU64::new(0),
+        }
+    }
+
+    /// Register a callback that is told how long a request is waiting for the limiter.
+    /// A wait of zero means the request is no longer waiting. Every callback is told about every wait,
+    /// since the requests of everything sharing the limiter wait in the same bucket.
+    pub fn on_wait(&self, observer: impl Fn(Duration) + Send + Sync + 'static) -> ObserverId {
+        let id = ObserverId(self.next_observer.fetch_add(1, Ordering::SeqCst));
+        self.observers.lock().unwrap().push((id, Box::new(observer)));
+        id
+    }
+
+    /// Remove a wait callback, leaving the others registered.
+    pub fn clear_on_wait(&self, id: ObserverId) {
+        self.observers.lock().unwrap().retain(|(other, _)| *other != id);
+    }
+
+    fn notify(&self, wait: Duration) {
+        for (_, observer) in self.observers.lock().unwrap().iter() {
+            observer(wait);
+        }
+    }
+
+    /// Block until a request is allowed to be made.
+    pub fn acquire(&self) {
+        let mut waited = false;
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                state.refill(self.capacity, now);
+                match state.blocked_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        state.blocked_until = None;
+                        if state.tokens >= 1.0 {
+                            state.tokens -= 1.0;
+                            break;
+                        }
+                        Duration::try_from_secs_f64((1.0 - state.tokens) / state.rate).unwrap_or(WAIT_TICK)
+                    }
+                }
+            };
+            waited = true;
+            self.notify(wait);
+            sleep(wait.min(WAIT_TICK));
+        }
+        if waited {
+            self.notify(Duration::ZERO);
+        }
+    }
+
+    /// Adapt the bucket to the rate limit headers of a response.
+    ///
+    /// `remaining` is the number of requests left in the current window,
+    /// and `reset` is the number of seconds until the window ends.
+    /// Values that are negative or not finite are ignored, and a reset is waited out for ten minutes at most.
+    pub fn observe(&self, remaining: Option<f64>, reset: Option<f64>) {
+        let valid = |value: Option<f64>| value.filter(|value| value.is_finite() && *value >= 0.0);
+        let (remaining, reset) = (valid(remaining), valid(reset));
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        state.refill(self.capacity, now);
+        match (remaining, reset) {
+            (Some(remaining), Some(reset)) if remaining < 1.0 => {
+                state.tokens = 0.0;
+                state.blocked_until = server_wait(reset, MAX_RESET).map(|wait| now + wait);
+            }
+            (Some(remaining), Some(reset)) => {
+                // Spread the remaining requests evenly over the rest of the window.
+                state.rate = (remaining / reset.max(1.0)).max(MIN_RATE);
+                state.tokens = state.tokens.min(remaining);
+            }
+            (Some(remaining), None) => {
+                state.tokens = state.tokens.min(remaining);
+            }
+            _ => state.rate = self.default_rate,
+        }
+    }
+
+    /// Stop all requests for an exponentially growing amount of time,
+    /// after a 429 or 5xx response. If the server asked for a specific
+    /// wait with `Retry-After`, wait at least that long.
+    pub fn back_off(&self, retry_after: Option<Duration>) {
+        let mut state = self.state.lock().unwrap();
+        state.backoff = if state.backoff.is_zero() {
+            INITIAL_BACKOFF
+        } else {
+            (state.backoff * 2).min(MAX_BACKOFF)
+        };
+        let wait = retry_after.map_or(state.backoff, |retry_after| retry_after.max(state.backoff));
+        state.tokens = 0.0;
+        state.blocked_until = Some(Instant::now() + wait);
+    }
+
+    /// Reset the backoff after a successful response.
+    pub fn succeed(&self) {
+        self.state.lock().unwrap().backoff = Duration::ZERO;
+    }
+}
+
+impl Default for RateLimiter {
+    /// Reddit allows anonymous clients roughly one request per second.
+    fn default() -> Self {
+        Self::new(10, 60.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_rate_of_zero_is_raised_to_the_minimum() {
+        assert_eq!(RateLimiter::new(10, 0.0).state.lock().unwrap().rate, MIN_RATE);
+        assert_eq!(RateLimiter::new(10, f64::NAN).state.lock().unwrap().rate, MIN_RATE);
+    }
+
+    #[test]
+    fn observed_rate_never_drops_to_zero() {
+        let limiter = RateLimiter::new(1, 60.0);
+        limiter.observe(Some(1.0), Some(1e300));
+        assert_eq!(limiter.state.lock().unwrap().rate, MIN_RATE);
+    }
+
+    #[test]
+    fn unusable_headers_are_ignored_or_capped() {
+        let limiter = RateLimiter::new(1, 60.0);
+        for reset in [f64::INFINITY, f64::NAN, -1.0] {
+            limiter.observe(Some(0.0), Some(reset));
+            assert_eq!(limiter.state.lock().unwrap().blocked_until, None);
+        }
+        limiter.observe(Some(0.0), Some(1e300));
+        let blocked_until = limiter.state.lock().unwrap().blocked_until.unwrap();
+        assert!(blocked_until <= Instant::now() + MAX_RESET);
+
+        assert_eq!(retry_after(f64::INFINITY), None);
+        assert_eq!(retry_after(f64::NAN), None);
+        assert_eq!(retry_after(-5.0), None);
+        assert_eq!(retry_after(1e300), Some(MAX_BACKOFF));
+        assert_eq!(retry_after(3.0), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn every_observer_is_told_until_it_is_removed() {
+        let limiter = RateLimiter::new(1, 60.0);
+        let (first, second) = (std::sync::Arc::new(AtomicU64::new(0)), std::sync::Arc::new(AtomicU64::new(0)));
+        let count = |counter: &std::sync::Arc<AtomicU64>| {
+            let counter = counter.clone();
+            move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        };
+        let first_id = limiter.on_wait(count(&first));
+        limiter.on_wait(count(&second));
+        limiter.notify(Duration::ZERO);
+        limiter.clear_on_wait(first_id);
+        limiter.notify(Duration::ZERO);
+        assert_eq!((first.load(Ordering::SeqCst), second.load(Ordering::SeqCst)), (1, 2));
+    }
+}
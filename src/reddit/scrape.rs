@@ -1,20 +1,134 @@
-use roux::{Subreddit, reply::MaybeReplies};
-use indicatif::{ProgressBar, MultiProgress, ProgressStyle};
-use std::{thread::sleep, time::Duration, path::PathBuf};
-use super::Data;
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Duration, sync::Arc};
+use super::{Client, Comments, FailedPost, Filters, ProgressBars, Scraper, ScraperConfig, Thing, DEFAULT_WORKERS};
 
 #[derive(Clone, Debug)]
 pub enum ScrapeError {
+    /// The request could not be sent, or the connection was lost.
+    Network(String),
+    /// Reddit responded with an unexpected HTTP status.
+    Status { status: u16, url: String },
+    /// Reddit responded with HTTP 429 too many times.
+    RateLimited { retry_after: Option<Duration> },
+    /// Reddit's response could not be parsed.
+    Parse(String),
+    /// The subreddit or post does not exist.
+    NotFound(String),
+    /// The subreddit has been banned.
+    Banned(String),
+    /// The subreddit is private or quarantined.
+    Private(String),
+    /// An access token could not be obtained with the configured credentials.
+    Auth(String),
+    /// The API rejected the access token.
+    Unauthorized,
+    /// What was asked for can't be collected as given, such as a thread link that isn't one.
+    InvalidInput(String),
     RedditError(String),
     CouldNotRead(String)
 }
 
-impl<E> From<E> for ScrapeError where E: std::error::Error {
-    fn from(err: E) -> Self {
+impl ScrapeError {
+    /// Is this error likely to go away if the request is retried?
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network(_) | Self::RateLimited { .. } | Self::Unauthorized => true,
+            Self::Status { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Network(err) => write!(f, "network error: {err}"),
+            Self::Status { status, url } => write!(f, "HTTP {status} from {url}"),
+            Self::RateLimited { retry_after: Some(wait) } => write!(f, "rate limited, retry after {:.0}s", wait.as_secs_f64()),
+            Self::RateLimited { retry_after: None } => write!(f, "rate limited"),
+            Self::Parse(err) => write!(f, "could not parse response: {err}"),
+            Self::NotFound(what) => write!(f, "{what} not found"),
+            Self::Banned(what) => write!(f, "{what} is banned"),
+            Self::Private(what) => write!(f, "{what} is private"),
+            Self::Auth(err) => write!(f, "could not authenticate: {err}"),
+            Self::Unauthorized => write!(f, "access token was rejected"),
+            Self::InvalidInput(err) => write!(f, "invalid input: {err}"),
+            Self::RedditError(err) => write!(f, "{err}"),
+            Self::CouldNotRead(what) => write!(f, "could not read {what}"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ScrapeError {
+    fn from(err: reqwest::Error) -> Self {
+        match (err.status(), err.url()) {
+            (Some(status), Some(url)) => Self::Status { status: status.as_u16(), url: url.to_string() },
+            _ if err.is_decode() => Self::Parse(err.to_string()),
+            _ => Self::Network(err.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ScrapeError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Parse(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for ScrapeError {
+    fn from(err: std::io::Error) -> Self {
+        Self::RedditError(format!("{err:?}"))
+    }
+}
+
+impl From<indicatif::style::TemplateError> for ScrapeError {
+    fn from(err: indicatif::style::TemplateError) -> Self {
         Self::RedditError(format!("{err:?}"))
     }
 }
 
+/// A subreddit that could not be collected at all.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FailedSubreddit {
+    /// The subreddit's name.
+    pub name: String,
+    /// Why the subreddit could not be collected.
+    pub error: String,
+}
+
+/// What went wrong during a collection run, so we know how complete each dataset is.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScrapeReport {
+    /// The subreddits that were collected, with the number of posts that were saved.
+    pub collected: Vec<(String, usize)>,
+    /// The subreddits that could not be collected.
+    pub failed_subreddits: Vec<FailedSubreddit>,
+    /// The posts that could not be collected, by subreddit.
+    pub failed_posts: Vec<(String, FailedPost)>,
+}
+
+impl ScrapeReport {
+    pub fn is_complete(&self) -> bool {
+        self.failed_subreddits.is_empty() && self.failed_posts.is_empty()
+    }
+}
+
+impl fmt::Display for ScrapeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, posts) in &self.collected {
+            let failed = self.failed_posts.iter().filter(|(subreddit, _)| subreddit == name).count();
+            writeln!(f, "r/{name}: collected {posts}/{} posts", posts + failed)?;
+        }
+        for (name, post) in &self.failed_posts {
+            writeln!(f, "r/{name}: failed to collect post {} \"{}\": {}", post.id, post.title, post.error)?;
+        }
+        for subreddit in &self.failed_subreddits {
+            writeln!(f, "r/{}: failed to collect subreddit: {}", subreddit.name, subreddit.error)?;
+        }
+        Ok(())
+    }
+}
+
 
 pub trait Scrape<T> {
     fn scrape(info: T) -> Result<Box<Self>, ScrapeError>;
@@ -22,60 +136,77 @@ pub trait Scrape<T> {
 
 impl Scrape<&str> for super::Subreddit {
     fn scrape(subreddit: &str) -> Result<Box<Self>, ScrapeError> {
-        Self::scrape(Subreddit::new(subreddit))
+        Self::scrape(roux::Subreddit::new(subreddit))
     }
 }
 
+/// Build a comment and all of its replies, leaving out the replies excluded by the shared filters.
 impl Scrape<roux::comment::CommentData> for super::Comment {
     fn scrape(comment: roux::comment::CommentData) -> Result<Box<Self>, ScrapeError> {
-        Ok(Box::new(Self {
-            body: comment.body.unwrap_or(String::new()),
-            score: comment.score.unwrap_or(0),
-            comments: match comment.replies {
-                Some(MaybeReplies::Reply(raw_replies)) => {
-                    let mut replies = vec![];
-                    for reply in raw_replies.data.children {
-                        replies.push(*Self::scrape(reply.data)?);
-                    }
-                    replies
-                }
-                _ => vec![]
-            }
-        }))
+        let comment = Thing { data: comment };
+        match Filters::shared().comments(vec![comment], &mut vec![]).pop() {
+            Some(comment) => Ok(Box::new(comment)),
+            None => Err(ScrapeError::CouldNotRead("comment".to_string())),
+        }
     }
 }
 
+/// Collect a post's comments with the default scraper config.
 impl Scrape<roux::submission::SubmissionData> for super::Post {
     fn scrape(post: roux::submission::SubmissionData) -> Result<Box<Self>, ScrapeError> {
-        let subreddit = Subreddit::new(&post.subreddit);
-        Ok(Box::new(Self {
+        let raw_comments = Client::shared().article_comments(&post.subreddit, &post.id, None, Some(COMMENTS_PER_POST))?;
+        Self::scrape((post, raw_comments))
+    }
+}
+
+/// Build a post from its listing and the comments that were retrieved for it.
+/// Comments excluded by the shared filters are left out, and recorded on the post.
+impl Scrape<(roux::submission::SubmissionData, Comments)> for super::Post {
+    fn scrape((post, raw_comments): (roux::submission::SubmissionData, Comments)) -> Result<Box<Self>, ScrapeError> {
+        Ok(Box::new(Self::from_listing(post, raw_comments, &Filters::shared())))
+    }
+}
+
+impl super::Post {
+    /// Build a post from its listing and the comments that were retrieved for it.
+    /// Comments excluded by `filters` are left out, and recorded on the post.
+    pub fn from_listing(post: roux::submission::SubmissionData, raw_comments: Comments, filters: &Filters) -> Self {
+        let mut filtered = vec![];
+        let comments = filters.comments(raw_comments.data.children, &mut filtered);
+        Self {
+            id: post.id,
+            created_utc: post.created_utc,
+            subreddit: post.subreddit,
             title: post.title,
             not_safe_for_work: post.over_18,
             locked: post.locked,
             body: post.selftext,
             score: post.score as i32,
-            comments: {
-                let raw_comments = subreddit.article_comments(&post.id, None, Some(15))?;
-
-                let mut comments = vec![];
-                for comment in raw_comments.data.children {
-                    comments.push(*super::Comment::scrape(comment.data)?);
-                }
-                comments
-            }
-        }))
+            comments,
+            filtered,
+            crosspost_from: None,
+        }
     }
 }
 
-const WAIT_BETWEEN_POSTS_SECONDS: f64 = 0.0;
-const WAIT_BETWEEN_SUBREDDITS_SECONDS: f64 = 3.0;
-const POSTS_PER_SUBREDDIT: u32 = 100;
-const COMMENTS_PER_POST: u32 = 10;
+/// The subreddit a listed post was crossposted from, in lowercase, if it is a crosspost.
+/// `roux` leaves out the crosspost fields, so they are read from the raw listing.
+pub(crate) fn crosspost_origin(raw_post: &serde_json::Value) -> Option<String> {
+    raw_post["crosspost_parent_list"].as_array()?.first()?["subreddit"].as_str().map(canonical_name)
+}
+
+/// The name a subreddit's dataset is saved under. Subreddit names are case-insensitive,
+/// so datasets are named in lowercase, like the subreddits a crawl discovers.
+pub fn canonical_name(subreddit: &str) -> String {
+    subreddit.trim().to_lowercase()
+}
+
+pub(crate) const COMMENTS_PER_POST: u32 = 10;
 
 impl Scrape<roux::Subreddit> for super::Subreddit {
     fn scrape(subreddit: roux::Subreddit) -> Result<Box<Self>, ScrapeError> {
         let name = subreddit.name.clone();
-        let results = *Vec::scrape(&[subreddit.name])?;
+        let results = *Vec::scrape(&[subreddit.name][..])?;
         match results.into_iter().next() {
             Some(val) => Ok(Box::new(val)),
             None => Err(ScrapeError::CouldNotRead(name))
@@ -84,161 +215,30 @@ impl Scrape<roux::Subreddit> for super::Subreddit {
 }
 
 
-impl<T> Scrape<&[T]> for Vec<super::Subreddit> where T: AsRef<str> {
+impl<T> Scrape<&[T]> for Vec<super::Subreddit> where T: AsRef<str> + Sync {
     fn scrape(subreddits: &[T]) -> Result<Box<Self>, ScrapeError> {
-        let subreddit_style = ProgressStyle::with_template(
-            " [{elapsed_precise}] {prefix:<22} {bar:30.cyan/red} {pos}/{len} {msg} {spinner}",
-        )?;
-        let submission_style = ProgressStyle::with_template(
-            " [{elapsed_precise}] {prefix:<22} {bar:30.cyan/red} {percent:>3}% [{per_sec:10}] {msg} {spinner}",
-        )?;
-
-        let multi_bar = MultiProgress::new();
-        let subreddit_bar = multi_bar.add(ProgressBar::new(subreddits.len() as u64));
-        subreddit_bar.set_style(subreddit_style);
-        subreddit_bar.set_prefix("Collecting subreddits");
-        let post_bar = multi_bar.insert_after(&subreddit_bar, ProgressBar::new(100));
-        post_bar.set_style(submission_style.clone());
-        post_bar.set_prefix("Collecting posts");
-        let comment_bar = multi_bar.insert_after(&post_bar, ProgressBar::new(100));
-        comment_bar.set_style(submission_style);
-        comment_bar.set_prefix("Collecting comments");
-        
-        
-        let mut result = vec![];
-        for subreddit_name in subreddits {
-            let output_path = format!("./data/{}.json", subreddit_name.as_ref());
-
-            if PathBuf::from(&output_path).exists() {
-                subreddit_bar.set_message(format!("Already have data for {}, skipping", subreddit_name.as_ref()));
-                subreddit_bar.inc(1);
-                continue;
-            }
-
-            subreddit_bar.set_message(format!("r/{}", subreddit_name.as_ref()));
-            
-            post_bar.reset();
-            comment_bar.reset();
-            
-            post_bar.set_message("Retrieving post IDs...");
-            
-            
-            let subreddit = Subreddit::new(subreddit_name.as_ref());
-            let raw_posts = match subreddit.hot(POSTS_PER_SUBREDDIT, None) {
-                Ok(posts) => posts,
-                Err(_) => {
-                    post_bar.set_message("Error retrieving post");
-                    sleep(Duration::from_millis(500));
-                    continue
-                }
-            };
-            let total_post_count = raw_posts.data.children.len() as u64;
-            let mut posts = vec![];
-
-            post_bar.set_message(format!("Retrieved {} post IDs", total_post_count));
-            post_bar.reset();
-            post_bar.set_length(total_post_count);
-
-            let mut raw_comments = vec![];
-            for (i, raw_post) in raw_posts.data.children.iter().enumerate() {
-                post_bar.set_message(format!("Retrieving post comments {i}/{total_post_count}"));
-                subreddit_bar.tick();
-                comment_bar.tick();
-                raw_comments.push(subreddit.article_comments(&raw_post.data.id, None, Some(COMMENTS_PER_POST)));
-                post_bar.inc(1);
-            }
-            post_bar.reset();
-            for (i, (raw_post, raw_comments)) in raw_posts.data.children.iter().zip(raw_comments).enumerate() {
-                let post = &raw_post.data;
-                for i in 0..(WAIT_BETWEEN_POSTS_SECONDS * 10.0) as usize {
-                    post_bar.set_message(format!("Waiting {}/{}s for rate limit...", i/10, WAIT_BETWEEN_POSTS_SECONDS));
-                    subreddit_bar.tick();
-                    post_bar.tick();
-                    comment_bar.tick();
-                    sleep(Duration::from_millis(100));
-                }
-                post_bar.set_message(format!("Awaiting post {i}/{total_post_count} comments..."));
-                let raw_comments = match raw_comments {
-                    Ok(c) => c,
-                    Err(_) => {
-                        post_bar.set_message("Error retrieving post");
-                        sleep(Duration::from_millis(500));
-                        continue;
-                    }
-                };
-                post_bar.set_message(format!("Retrieved post {i}/{total_post_count} comments"));
-                posts.push(super::Post {
-                    title: post.title.clone(),
-                    not_safe_for_work: post.over_18,
-                    locked: post.locked,
-                    body: post.selftext.clone(),
-                    score: post.score as i32,
-                    comments: {
-                        if raw_comments.data.children.is_empty() {
-                            vec![]
-                        } else {
-                            comment_bar.reset();
-                            comment_bar.set_length(raw_comments.data.children.len() as u64);
-                            
-                            comment_bar.set_message(format!("Processing {} replies...", raw_comments.data.children.len()));
-                            let mut comments = vec![];
-                            for comment in raw_comments.data.children {
-    
-                                comment_bar.set_message("Retrieving replies...");
-                                comments.push(*super::Comment::scrape(comment.data)?);
-                                comment_bar.set_message("Retrieved replies");
-                                
-                                subreddit_bar.tick();
-                                post_bar.tick();
-                                comment_bar.inc(1);
-                            }
-                            comments
-                        }
-                    }
-                });
-                comment_bar.set_message("Done with post replies");
-                post_bar.set_message(format!("Finished post {i}/{}", total_post_count));
-                post_bar.inc(1);
-            }
-            post_bar.set_message("Finished subreddit posts");
-
-            subreddit_bar.set_message("Processing subreddit...");
-            let about = subreddit.about();
-            let subreddit = super::Subreddit {
-                name: subreddit.name.clone(),
-                description: match &about {
-                    Ok(s) => s.public_description.clone().unwrap_or("".to_string()),
-                    Err(_) => "".to_string()
-                },
-                subscribers: match about {
-                    Ok(s) => s.subscribers.unwrap_or(0),
-                    Err(_) => 0
-                },
-                posts
-            };
-            subreddit_bar.set_message(format!("Saving to {output_path}..."));
-            subreddit.save(&PathBuf::from(output_path.clone()));
-            result.push(subreddit);
-            subreddit_bar.set_message(format!("Saved to {output_path}"));
-            subreddit_bar.inc(1);
-
-            for i in 0..(WAIT_BETWEEN_SUBREDDITS_SECONDS * 10.0) as usize {
-                subreddit_bar.set_message(format!("Waiting {}/{}s for rate limit...", i/10, WAIT_BETWEEN_SUBREDDITS_SECONDS));
-                subreddit_bar.tick();
-                post_bar.tick();
-                comment_bar.tick();
-                sleep(Duration::from_millis(100));
-            }
+        Self::scrape((subreddits, DEFAULT_WORKERS))
+    }
+}
+
+/// Collect subreddits into `./data` with the shared client and filters, drawing progress bars.
+/// Use a `Scraper` directly to collect anywhere else, or with other limits.
+impl<T> Scrape<(&[T], usize)> for Vec<super::Subreddit> where T: AsRef<str> + Sync {
+    fn scrape((subreddits, workers): (&[T], usize)) -> Result<Box<Self>, ScrapeError> {
+        let bars = Arc::new(ProgressBars::new(subreddits.len(), workers)?);
+        let scraper = {
+            let bars = bars.clone();
+            Scraper::builder()
+                .config(ScraperConfig { workers, filters: Filters::shared(), ..Default::default() })
+                .progress(move |progress| bars.update(progress))
+                .build()
+        };
+        let (subreddits, report) = scraper.run(subreddits);
+        bars.finish();
+
+        if !report.is_complete() {
+            eprint!("{report}");
         }
-        subreddit_bar.set_message("Finished");
-        post_bar.set_message("Finished");
-        comment_bar.set_message("Finished");
-        post_bar.finish_and_clear();
-        comment_bar.finish_and_clear();
-        subreddit_bar.finish_and_clear();
-
-        multi_bar.clear()?;
-        
-        Ok(Box::new(result))
+        Ok(Box::new(subreddits))
     }
 }
@@ -0,0 +1,519 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::{path::PathBuf, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+use crate::events::{self, Event};
+use roux::submission::SubmissionData;
+use super::{canonical_name, crosspost_origin, Client, Data, FailedPost, FailedSubreddit, Filtered, Filters, Post, ScrapeError, ScrapeReport, Subreddit, User, COMMENTS_PER_POST};
+
+const POSTS_PER_SUBREDDIT: u32 = 100;
+/// How many subreddits are collected at once when no worker count is given.
+pub const DEFAULT_WORKERS: usize = 4;
+
+/// What a `Scraper` collects, and how.
+#[derive(Clone, Debug)]
+pub struct ScraperConfig {
+    /// How many hot posts to collect from each subreddit.
+    pub posts_per_subreddit: u32,
+    /// How many top-level comments to collect for each post.
+    pub comments_per_post: u32,
+    /// How many subreddits to collect at once, and how many posts of each subreddit to fetch comments for at once.
+    /// The workers share the client's rate limit.
+    pub workers: usize,
+    /// Don't collect subreddits the output sink already has.
+    pub skip_existing: bool,
+    /// Which posts and comments to leave out.
+    pub filters: Filters,
+}
+
+impl Default for ScraperConfig {
+    fn default() -> Self {
+        Self {
+            posts_per_subreddit: POSTS_PER_SUBREDDIT,
+            comments_per_post: COMMENTS_PER_POST,
+            workers: DEFAULT_WORKERS,
+            skip_existing: true,
+            filters: Filters::default(),
+        }
+    }
+}
+
+/// Where a `Scraper` puts the subreddits it collects.
+pub trait Sink: Send + Sync {
+    /// Whether the sink already has a subreddit.
+    fn contains(&self, subreddit: &str) -> bool;
+    /// Store a collected subreddit.
+    fn put(&self, subreddit: &Subreddit) -> Result<(), ScrapeError>;
+    /// Store a collected user.
+    fn put_user(&self, user: &User) -> Result<(), ScrapeError>;
+    /// Read back a subreddit the sink has, to add to it. Sinks that can't be read from have nothing.
+    fn get(&self, _subreddit: &str) -> Option<Subreddit> {
+        None
+    }
+}
+
+/// Saves each subreddit to `{dir}/{name}.json`, under its canonical name, and each user to `{dir}/users/{name}.json`.
+pub struct DirectorySink {
+    pub dir: PathBuf,
+}
+
+impl DirectorySink {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, subreddit: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", canonical_name(subreddit)))
+    }
+}
+
+impl Default for DirectorySink {
+    /// The `./data` directory the `analyze` and `graph` commands read from.
+    fn default() -> Self {
+        Self::new("./data")
+    }
+}
+
+impl Sink for DirectorySink {
+    fn contains(&self, subreddit: &str) -> bool {
+        self.path(subreddit).exists()
+    }
+
+    fn put(&self, subreddit: &Subreddit) -> Result<(), ScrapeError> {
+        std::fs::create_dir_all(&self.dir)?;
+        subreddit.save(&self.path(&subreddit.name));
+        Ok(())
+    }
+
+    fn put_user(&self, user: &User) -> Result<(), ScrapeError> {
+        let dir = self.dir.join("users");
+        std::fs::create_dir_all(&dir)?;
+        user.save(&dir.join(format!("{}.json", user.name)));
+        Ok(())
+    }
+
+    fn get(&self, subreddit: &str) -> Option<Subreddit> {
+        let path = self.path(subreddit);
+        path.exists().then(|| {
+            let mut subreddit = Subreddit::default();
+            subreddit.restore(&path);
+            subreddit
+        })
+    }
+}
+
+/// Keeps nothing, for callers that only want the subreddits `Scraper::run` returns.
+pub struct Discard;
+
+impl Sink for Discard {
+    fn contains(&self, _subreddit: &str) -> bool {
+        false
+    }
+
+    fn put(&self, _subreddit: &Subreddit) -> Result<(), ScrapeError> {
+        Ok(())
+    }
+
+    fn put_user(&self, _user: &User) -> Result<(), ScrapeError> {
+        Ok(())
+    }
+}
+
+/// What a `Scraper` is doing, reported to its progress callback.
+/// `worker` is the index of the worker the update comes from.
+#[derive(Clone, Debug)]
+pub enum Progress {
+    /// The sink already has the subreddit, so it was not collected again.
+    Skipped { worker: usize, subreddit: String },
+    /// A worker started collecting a subreddit.
+    SubredditStarted { worker: usize, subreddit: String },
+    /// The subreddit's posts were listed, and their comments are about to be collected.
+    PostsListed { worker: usize, subreddit: String, posts: usize },
+    /// A post was collected, filtered out, or failed. `index` is the post's place in the listing, counting from one.
+    PostDone { worker: usize, subreddit: String, index: usize, error: Option<String> },
+    /// A subreddit was collected and stored.
+    SubredditDone { worker: usize, subreddit: String, posts: usize },
+    /// A subreddit could not be collected at all.
+    SubredditFailed { worker: usize, subreddit: String, error: String },
+    /// A worker finished, because there are no subreddits left.
+    WorkerDone { worker: usize },
+    /// Requests are waiting this long for the rate limit. Zero means they are no longer waiting.
+    Waiting(Duration),
+}
+
+type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// Collects subreddits with a pool of workers, storing each one in a sink as it is done.
+///
+/// ```no_run
+/// use reddit_analyzer::{DirectorySink, Scraper, ScraperConfig};
+///
+/// let scraper = Scraper::builder()
+///     .config(ScraperConfig { posts_per_subreddit: 25, ..Default::default() })
+///     .sink(DirectorySink::new("datasets"))
+///     .progress(|progress| eprintln!("{progress:?}"))
+///     .build();
+/// let (subreddits, report) = scraper.run(&["rust", "programming"]);
+/// ```
+pub struct Scraper {
+    client: Client,
+    config: ScraperConfig,
+    sink: Box<dyn Sink>,
+    progress: Option<ProgressCallback>,
+}
+
+/// Builds a `Scraper`. Anything left unset falls back to the shared client,
+/// the default config, and saving to `./data`.
+#[derive(Default)]
+pub struct ScraperBuilder {
+    client: Option<Client>,
+    config: ScraperConfig,
+    sink: Option<Box<dyn Sink>>,
+    progress: Option<ProgressCallback>,
+}
+
+impl ScraperBuilder {
+    /// The client requests are made with.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn config(mut self, config: ScraperConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn sink(mut self, sink: impl Sink + 'static) -> Self {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    /// A callback that is told what the workers are doing. It is called from the worker threads.
+    pub fn progress(mut self, progress: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    pub fn build(self) -> Scraper {
+        Scraper {
+            client: self.client.unwrap_or_else(Client::shared),
+            config: self.config,
+            sink: self.sink.unwrap_or_else(|| Box::new(DirectorySink::default())),
+            progress: self.progress,
+        }
+    }
+}
+
+impl Scraper {
+    pub fn builder() -> ScraperBuilder {
+        ScraperBuilder::default()
+    }
+
+    pub fn config(&self) -> &ScraperConfig {
+        &self.config
+    }
+
+    /// The client requests are made with.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Where collected datasets are stored.
+    pub fn sink(&self) -> &dyn Sink {
+        self.sink.as_ref()
+    }
+
+    pub(super) fn report(&self, progress: Progress) {
+        if let Some(callback) = &self.progress {
+            callback(&progress);
+        }
+    }
+
+    /// Collect the subreddits, returning the ones that were collected and a report of what went wrong.
+    /// Subreddits the sink already has are skipped, unless the config says otherwise.
+    pub fn run<T>(&self, subreddits: &[T]) -> (Vec<Subreddit>, ScrapeReport) where T: AsRef<str> + Sync {
+        self.reporting_waits(|| {
+            let queue = Mutex::new(subreddits.iter());
+            let result = Mutex::new(vec![]);
+            let report = Mutex::new(ScrapeReport::default());
+            thread::scope(|scope| {
+                for worker in 0..self.config.workers.max(1).min(subreddits.len()) {
+                    let (queue, result, report) = (&queue, &result, &report);
+                    scope.spawn(move || {
+                        // Take the next subreddit off the queue until there are none left.
+                        let next = || queue.lock().unwrap().next();
+                        while let Some(subreddit_name) = next() {
+                            let name = &canonical_name(subreddit_name.as_ref());
+                            if self.config.skip_existing && self.sink.contains(name) {
+                                self.report(Progress::Skipped { worker, subreddit: name.to_string() });
+                                continue;
+                            }
+
+                            match self.collect_into_sink(worker, name, || self.collect(worker, name)) {
+                                Ok(subreddit) => {
+                                    let mut report = report.lock().unwrap();
+                                    report.collected.push((subreddit.name.clone(), subreddit.posts.len()));
+                                    report.failed_posts.extend(subreddit.failed_posts.iter().map(|post| (subreddit.name.clone(), post.clone())));
+                                    drop(report);
+                                    result.lock().unwrap().push(subreddit);
+                                }
+                                Err(err) => {
+                                    report.lock().unwrap().failed_subreddits.push(FailedSubreddit { name: name.to_string(), error: err.to_string() });
+                                }
+                            }
+                        }
+                        self.report(Progress::WorkerDone { worker });
+                    });
+                }
+            });
+            (result.into_inner().unwrap(), report.into_inner().unwrap())
+        })
+    }
+
+    /// Fail if the sink already has a dataset named `name`, unless the config collects existing ones again.
+    /// `what` is what was going to be collected, such as "threads".
+    pub(super) fn check_new(&self, name: &str, what: &str) -> Result<(), ScrapeError> {
+        match self.config.skip_existing && self.sink.contains(name) {
+            true => Err(ScrapeError::InvalidInput(format!("a dataset named {name} already exists, collect the {what} under another name"))),
+            false => Ok(()),
+        }
+    }
+
+    /// Run `f`, telling the progress callback while requests wait for the rate limit.
+    pub(super) fn reporting_waits<R>(&self, f: impl FnOnce() -> R) -> R {
+        let observer = self.progress.clone().map(|progress| self.client.limiter().on_wait(move |wait| progress(&Progress::Waiting(wait))));
+        let result = f();
+        if let Some(observer) = observer {
+            self.client.limiter().clear_on_wait(observer);
+        }
+        result
+    }
+
+    /// Collect a dataset and store it in the sink, telling the progress callback and the event stream how it went.
+    pub(super) fn collect_into_sink(&self, worker: usize, name: &str, collect: impl FnOnce() -> Result<Subreddit, ScrapeError>) -> Result<Subreddit, ScrapeError> {
+        self.report(Progress::SubredditStarted { worker, subreddit: name.to_string() });
+        events::emit(Event::SubredditStarted { subreddit: name.to_string() });
+        let started = Instant::now();
+        match collect().and_then(|subreddit| self.sink.put(&subreddit).map(|_| subreddit)) {
+            Ok(subreddit) => {
+                events::emit(Event::SubredditFinished {
+                    subreddit: name.to_string(),
+                    posts: subreddit.posts.len(),
+                    failed_posts: subreddit.failed_posts.len(),
+                    filtered_posts: subreddit.filtered.len(),
+                    elapsed_ms: started.elapsed().as_millis(),
+                });
+                self.report(Progress::SubredditDone { worker, subreddit: name.to_string(), posts: subreddit.posts.len() });
+                Ok(subreddit)
+            }
+            Err(err) => {
+                events::emit(Event::Error { subreddit: name.to_string(), id: None, error: err.to_string() });
+                self.report(Progress::SubredditFailed { worker, subreddit: name.to_string(), error: err.to_string() });
+                Err(err)
+            }
+        }
+    }
+
+    /// Collect the hot posts of a subreddit and their comments.
+    /// Posts that can't be collected are recorded on the subreddit, instead of failing the whole subreddit.
+    pub fn collect(&self, worker: usize, subreddit: &str) -> Result<Subreddit, ScrapeError> {
+        let client = &self.client;
+        // The raw listing is read, since `roux` leaves out where crossposts came from.
+        let raw_posts = client.listing::<serde_json::Value>(&format!("/r/{subreddit}/hot.json"), &[], self.config.posts_per_subreddit)?;
+        let Collected { posts, failed_posts, filtered } = self.collect_posts(worker, subreddit, raw_posts)?;
+
+        let about = client.about(subreddit);
+        Ok(Subreddit {
+            name: subreddit.to_string(),
+            description: match &about {
+                Ok(s) => s.public_description.clone().unwrap_or("".to_string()),
+                Err(_) => "".to_string()
+            },
+            subscribers: match about {
+                Ok(s) => s.subscribers.unwrap_or(0),
+                Err(_) => 0
+            },
+            posts,
+            failed_posts,
+            filtered,
+        })
+    }
+
+    /// Collect the posts of a raw listing into the dataset `name`, leaving out the ones the filters exclude,
+    /// and fetching the comments of the rest.
+    pub(super) fn collect_posts(&self, worker: usize, name: &str, raw_posts: Vec<serde_json::Value>) -> Result<Collected, ScrapeError> {
+        self.report(Progress::PostsListed { worker, subreddit: name.to_string(), posts: raw_posts.len() });
+        let mut listed = vec![];
+        let mut filtered = vec![];
+        for (index, raw_post) in raw_posts.into_iter().enumerate() {
+            let crosspost_from = crosspost_origin(&raw_post);
+            let post: SubmissionData = serde_json::from_value(raw_post)?;
+            match self.config.filters.post_reason(&post) {
+                Some(reason) => {
+                    filtered.push(Filtered { id: post.id, reason });
+                    self.report(Progress::PostDone { worker, subreddit: name.to_string(), index: index + 1, error: None });
+                }
+                None => listed.push(Listed { index: index + 1, crosspost_from, post }),
+            }
+        }
+        let (posts, failed_posts) = self.fetch_comments(worker, name, listed);
+        Ok(Collected { posts, failed_posts, filtered })
+    }
+
+    /// Fetch the comments of listed posts on the worker pool, returning the posts in the listing's order,
+    /// and the ones whose comments couldn't be fetched. Each post's comments are fetched from the subreddit it is in.
+    pub(super) fn fetch_comments(&self, worker: usize, name: &str, listed: Vec<Listed>) -> (Vec<Post>, Vec<FailedPost>) {
+        let filters = &self.config.filters;
+        let results = self.on_pool(listed, |Listed { index, crosspost_from, post }| {
+            let (id, title) = (post.id.clone(), post.title.clone());
+            self.fetch_post(worker, name, index, (&id, &title), || {
+                let raw_comments = self.client.article_comments(&post.subreddit, &post.id, None, Some(self.config.comments_per_post))?;
+                Ok(Post { crosspost_from, ..Post::from_listing(post, raw_comments, filters) })
+            })
+        });
+        split_failed(results)
+    }
+
+    /// Fetch a post with `fetch`, telling the progress callback and the event stream how it went.
+    /// `index` is the post's place in what is being collected, and the ID and title are what it is known by so far.
+    pub(super) fn fetch_post(&self, worker: usize, name: &str, index: usize, (id, title): (&str, &str), fetch: impl FnOnce() -> Result<Post, ScrapeError>) -> Result<Post, FailedPost> {
+        let started = Instant::now();
+        match fetch() {
+            Ok(post) => {
+                events::emit(Event::PostFetched {
+                    subreddit: name.to_string(),
+                    id: post.id.clone(),
+                    comments: post.comments.len(),
+                    elapsed_ms: started.elapsed().as_millis(),
+                });
+                self.report(Progress::PostDone { worker, subreddit: name.to_string(), index, error: None });
+                Ok(post)
+            }
+            Err(err) => {
+                events::emit(Event::Error { subreddit: name.to_string(), id: Some(id.to_string()), error: err.to_string() });
+                self.report(Progress::PostDone { worker, subreddit: name.to_string(), index, error: Some(err.to_string()) });
+                Err(FailedPost { id: id.to_string(), title: title.to_string(), error: err.to_string() })
+            }
+        }
+    }
+
+    /// Run `fetch` on every item, spreading the items over the configured number of workers.
+    /// The results are in the order of the items.
+    pub(super) fn on_pool<I: Send, R: Send>(&self, items: Vec<I>, fetch: impl Fn(I) -> R + Sync) -> Vec<R> {
+        let workers = self.config.workers.max(1).min(items.len());
+        let queue = Mutex::new(items.into_iter().enumerate());
+        let done = Mutex::new(vec![]);
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| {
+                    let next = || queue.lock().unwrap().next();
+                    while let Some((i, item)) = next() {
+                        let result = fetch(item);
+                        done.lock().unwrap().push((i, result));
+                    }
+                });
+            }
+        });
+        let mut done = done.into_inner().unwrap();
+        done.sort_by_key(|(i, _)| *i);
+        done.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+/// A listed post whose comments are to be fetched.
+pub(super) struct Listed {
+    /// The post's place in what is being collected, counting from one.
+    pub index: usize,
+    pub crosspost_from: Option<String>,
+    pub post: SubmissionData,
+}
+
+/// The posts of a listing, split by whether they were collected, failed, or were filtered out.
+pub(super) struct Collected {
+    pub posts: Vec<Post>,
+    pub failed_posts: Vec<FailedPost>,
+    pub filtered: Vec<Filtered>,
+}
+
+/// Split fetched posts into the ones that were collected and the ones that failed.
+pub(super) fn split_failed(results: Vec<Result<Post, FailedPost>>) -> (Vec<Post>, Vec<FailedPost>) {
+    let mut posts = vec![];
+    let mut failed_posts = vec![];
+    for result in results {
+        match result {
+            Ok(post) => posts.push(post),
+            Err(failed) => failed_posts.push(failed),
+        }
+    }
+    (posts, failed_posts)
+}
+
+/// Draws a `Scraper`'s progress as a bar for the subreddits, and a bar per worker for the posts.
+pub struct ProgressBars {
+    multi_bar: MultiProgress,
+    subreddit_bar: ProgressBar,
+    post_bars: Vec<ProgressBar>,
+}
+
+impl ProgressBars {
+    pub fn new(subreddits: usize, workers: usize) -> Result<Self, ScrapeError> {
+        let subreddit_style = ProgressStyle::with_template(
+            " [{elapsed_precise}] {prefix:<22} {bar:30.cyan/red} {pos}/{len} {msg} {spinner}",
+        )?;
+        let submission_style = ProgressStyle::with_template(
+            " [{elapsed_precise}] {prefix:<22} {bar:30.cyan/red} {percent:>3}% [{per_sec:10}] {msg} {spinner}",
+        )?;
+
+        let multi_bar = MultiProgress::with_draw_target(events::draw_target());
+        let subreddit_bar = multi_bar.add(ProgressBar::new(subreddits as u64));
+        subreddit_bar.set_style(subreddit_style);
+        subreddit_bar.set_prefix("Collecting subreddits");
+
+        let post_bars = (0..workers.max(1).min(subreddits)).map(|worker| {
+            let post_bar = multi_bar.add(ProgressBar::new(0));
+            post_bar.set_style(submission_style.clone());
+            post_bar.set_prefix(format!("Worker {}", worker + 1));
+            post_bar.enable_steady_tick(Duration::from_millis(100));
+            post_bar
+        }).collect();
+        Ok(Self { multi_bar, subreddit_bar, post_bars })
+    }
+
+    /// Update the bars. Pass this to `ScraperBuilder::progress`.
+    pub fn update(&self, progress: &Progress) {
+        match progress {
+            Progress::Skipped { subreddit, .. } => {
+                self.subreddit_bar.println(format!("Already have data for {subreddit}, skipping"));
+                self.subreddit_bar.inc(1);
+            }
+            Progress::SubredditStarted { worker, subreddit } => {
+                let post_bar = &self.post_bars[*worker];
+                post_bar.reset();
+                post_bar.set_prefix(format!("r/{subreddit}"));
+                post_bar.set_message("Retrieving post IDs...");
+            }
+            Progress::PostsListed { worker, posts, .. } => self.post_bars[*worker].set_length(*posts as u64),
+            Progress::PostDone { worker, index, error, .. } => {
+                let post_bar = &self.post_bars[*worker];
+                let total = post_bar.length().unwrap_or(0);
+                match error {
+                    Some(err) => post_bar.set_message(format!("Error retrieving post {index}/{total}: {err}")),
+                    None => post_bar.set_message(format!("Retrieving post {index}/{total} comments...")),
+                }
+                post_bar.inc(1);
+            }
+            Progress::SubredditDone { .. } => self.subreddit_bar.inc(1),
+            Progress::SubredditFailed { subreddit, error, .. } => {
+                self.subreddit_bar.println(format!("Error retrieving r/{subreddit}: {error}"));
+                self.subreddit_bar.inc(1);
+            }
+            Progress::WorkerDone { worker } => self.post_bars[*worker].finish_and_clear(),
+            Progress::Waiting(wait) if wait.is_zero() => self.subreddit_bar.set_message(""),
+            Progress::Waiting(wait) => self.subreddit_bar.set_message(format!("Waiting {:.1}s for rate limit...", wait.as_secs_f64())),
+        }
+    }
+
+    pub fn finish(&self) {
+        self.subreddit_bar.finish_and_clear();
+        let _ = self.multi_bar.clear();
+    }
+}
@@ -0,0 +1,92 @@
+use super::{Collected, ScrapeError, Scraper, Subreddit};
+use std::{fmt, str::FromStr};
+
+/// How far back a search looks for posts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeRange {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+    #[default]
+    All,
+}
+
+impl TimeRange {
+    /// The value of Reddit's `t` search parameter.
+    pub fn as_param(&self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Year => "year",
+            Self::All => "all",
+        }
+    }
+}
+
+impl FromStr for TimeRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "hour" => Self::Hour,
+            "day" => Self::Day,
+            "week" => Self::Week,
+            "month" => Self::Month,
+            "year" => Self::Year,
+            "all" => Self::All,
+            _ => return Err(format!("unknown time range: {s}, expected hour, day, week, month, year or all")),
+        })
+    }
+}
+
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_param())
+    }
+}
+
+/// A Reddit search query, optionally restricted to some subreddits.
+#[derive(Clone, Debug)]
+pub struct Search {
+    /// The search query, such as "steam deck".
+    pub query: String,
+    /// The subreddits to search in. If empty, all of Reddit is searched.
+    pub subreddits: Vec<String>,
+    /// How far back to search.
+    pub time: TimeRange,
+    /// The most posts to collect.
+    pub limit: u32,
+}
+
+impl Search {
+    /// Describes the search, for the description of the dataset its results are collected into.
+    pub fn description(&self) -> String {
+        let mut description = match self.time {
+            TimeRange::All => format!("Search results for \"{}\" from all time", self.query),
+            time => format!("Search results for \"{}\" from the past {time}", self.query),
+        };
+        if !self.subreddits.is_empty() {
+            description += &format!(" in r/{}", self.subreddits.join(", r/"));
+        }
+        description
+    }
+}
+
+impl Scraper {
+    /// Collect the results of a search, and their comments, into a dataset named `name`, and store it in the sink.
+    /// Each post records the subreddit it came from, so the dataset can be split by subreddit later.
+    /// A dataset the sink already has is only collected again if the config doesn't skip existing ones.
+    pub fn search(&self, name: &str, search: &Search) -> Result<Subreddit, ScrapeError> {
+        self.check_new(name, "search results")?;
+        self.reporting_waits(|| self.collect_into_sink(0, name, || {
+            // The raw listing is read, since `roux` leaves out where crossposts came from.
+            let raw_posts = self.client().search::<serde_json::Value>(&search.query, &search.subreddits, search.time, search.limit)?;
+            let Collected { posts, failed_posts, filtered } = self.collect_posts(0, name, raw_posts)?;
+            Ok(Subreddit { name: name.to_string(), description: search.description(), subscribers: 0, posts, failed_posts, filtered })
+        }))
+    }
+}
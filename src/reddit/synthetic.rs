@@ -0,0 +1,365 @@
+use crate::{Attitude, Subject};
+use serde::{Deserialize, Serialize};
+use super::{Comment, Post, Subreddit};
+
+/// When the first synthetic post was submitted, in seconds since the Unix epoch.
+const START_UTC: f64 = 1_667_260_800.0;
+
+/// A description of a made-up subreddit, which `generate` turns into the same
+/// subreddit every time for the same seed. This lets the collect, analyze and graph
+/// steps be exercised without network access.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Synthetic {
+    /// The subreddit's name.
+    pub name: String,
+    /// Everything random is drawn from this seed.
+    pub seed: u64,
+    /// How many posts to generate.
+    pub posts: usize,
+    /// The average number of top-level comments on a post.
+    pub comments_per_post: f64,
+    /// The average number of replies to a top-level comment.
+    pub branching: f64,
+    /// How much the average number of replies shrinks at each level deeper, between 0 and 1.
+    pub depth_decay: f64,
+    /// The deepest a reply can be nested, where top-level comments are at depth 0.
+    pub max_depth: usize,
+    /// The median score of posts and comments.
+    pub score_median: f64,
+    /// How spread out scores are around the median, on a log scale.
+    pub score_spread: f64,
+    /// The share of posts that are links or images, so they have a title but no body.
+    pub link_posts: f64,
+    /// The subjects the subreddit talks about. Empty means any subject.
+    pub subjects: Vec<Subject>,
+}
+
+impl Default for Synthetic {
+    fn default() -> Self {
+        Self {
+            name: "synthetic".to_string(),
+            seed: 0,
+            posts: 50,
+            comments_per_post: 8.0,
+            branching: 1.5,
+            depth_decay: 0.5,
+            max_depth: 5,
+            score_median: 10.0,
+            score_spread: 1.5,
+            link_posts: 0.3,
+            subjects: vec![],
+        }
+    }
+}
+
+/// The attitude and subject a synthetic post or comment was written with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SyntheticLabel {
+    /// The ID of the post or comment.
+    pub id: String,
+    pub attitude: Attitude,
+    pub subject: Subject,
+}
+
+impl Synthetic {
+    /// Generate the subreddit.
+    pub fn generate(&self) -> Subreddit {
+        self.generate_labeled().0
+    }
+
+    /// Generate the subreddit, along with the attitude and subject each post and comment was written with.
+    pub fn generate_labeled(&self) -> (Subreddit, Vec<SyntheticLabel>) {
+        let mut generator = Generator { config: self, rng: Rng::new(self.seed), next_id: 0, labels: vec![] };
+        let posts = (0..self.posts).map(|i| generator.post(i)).collect();
+        let subreddit = Subreddit {
+            name: self.name.clone(),
+            description: format!("Synthetic subreddit generated from seed {}", self.seed),
+            subscribers: generator.rng.below(1_000_000) as u64 + 1_000,
+            posts,
+            failed_posts: vec![],
+            filtered: vec![],
+        };
+        (subreddit, generator.labels)
+    }
+}
+
+struct Generator<'a> {
+    config: &'a Synthetic,
+    rng: Rng,
+    next_id: u64,
+    labels: Vec<SyntheticLabel>,
+}
+
+impl Generator<'_> {
+    /// A new base36 ID, like the ones Reddit uses.
+    fn id(&mut self) -> String {
+        let mut n = self.next_id;
+        self.next_id += 1;
+        let mut id = vec![];
+        loop {
+            id.push(std::char::from_digit((n % 36) as u32, 36).unwrap());
+            n /= 36;
+            if n == 0 {
+                break;
+            }
+        }
+        id.iter().rev().collect()
+    }
+
+    fn subject(&mut self) -> Subject {
+        match self.config.subjects.as_slice() {
+            [] => *self.rng.choose(&Subject::VALUES),
+            subjects => *self.rng.choose(subjects),
+        }
+    }
+
+    /// A score drawn from a log-normal distribution around the median, occasionally downvoted below zero.
+    fn score(&mut self) -> i32 {
+        let score = (self.config.score_median.max(0.0) + 1.0).ln() + self.config.score_spread * self.rng.normal();
+        let score = score.exp().round() as i32 - 1;
+        if self.rng.chance(0.05) { -score.min(50) } else { score }
+    }
+
+    /// A sentence with the given attitude about a topic of the given subject.
+    fn text(&mut self, attitude: Attitude, subject: Subject) -> String {
+        let topic = *self.rng.choose(topics(subject));
+        self.sentence(attitude, topic)
+    }
+
+    /// A sentence with the given attitude about a topic.
+    fn sentence(&mut self, attitude: Attitude, topic: &str) -> String {
+        capitalize(&self.rng.choose(templates(attitude)).replace("{topic}", topic))
+    }
+
+    fn post(&mut self, index: usize) -> Post {
+        let id = self.id();
+        let subject = self.subject();
+        let attitude = *self.rng.choose(&Attitude::VALUES);
+        self.labels.push(SyntheticLabel { id: id.clone(), attitude, subject });
+
+        let topic = *self.rng.choose(topics(subject));
+        let title = capitalize(&self.rng.choose(TITLES).replace("{topic}", topic));
+        let body = if self.rng.chance(self.config.link_posts) {
+            String::new()
+        } else {
+            (0..1 + self.rng.below(3)).map(|_| self.sentence(attitude, topic)).collect::<Vec<_>>().join(" ")
+        };
+        let comments = (0..self.rng.poisson(self.config.comments_per_post))
+            .map(|_| self.comment(subject, 0))
+            .collect();
+        Post {
+            id,
+            created_utc: START_UTC + index as f64 * 3600.0 + self.rng.below(3600) as f64,
+            subreddit: self.config.name.clone(),
+            title,
+            not_safe_for_work: false,
+            locked: false,
+            body,
+            score: self.score(),
+            comments,
+            filtered: vec![],
+            crosspost_from: None,
+        }
+    }
+
+    /// A comment and its replies. Replies mostly stay on the subject of the thread.
+    fn comment(&mut self, thread_subject: Subject, depth: usize) -> Comment {
+        let id = self.id();
+        let subject = if self.rng.chance(0.8) { thread_subject } else { self.subject() };
+        let attitude = *self.rng.choose(&Attitude::VALUES);
+        self.labels.push(SyntheticLabel { id: id.clone(), attitude, subject });
+
+        let body = self.text(attitude, subject);
+        let replies = if depth < self.config.max_depth {
+            self.rng.poisson(self.config.branching * self.config.depth_decay.powi(depth as i32))
+        } else {
+            0
+        };
+        let comments = (0..replies).map(|_| self.comment(subject, depth + 1)).collect();
+        Comment {
+            id,
+            subreddit: self.config.name.clone(),
+            body,
+            score: self.score(),
+            comments,
+        }
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+const TITLES: &[&str] = &[
+    "What do you think about {topic}?",
+    "Finally got around to {topic}",
+    "{topic}, one year later",
+    "Can we talk about {topic}?",
+    "Unpopular opinion about {topic}",
+    "My experience with {topic}",
+];
+
+/// Sentence templates that read as each attitude.
+fn templates(attitude: Attitude) -> &'static [&'static str] {
+    match attitude {
+        Attitude::Inquisitive => &[
+            "Does anyone know how {topic} actually works?",
+            "Why is everyone talking about {topic} lately?",
+            "Can someone explain {topic} to me?",
+        ],
+        Attitude::Praise => &[
+            "{topic} is honestly amazing, great work.",
+            "I love {topic}, this made my day.",
+            "This is the best take on {topic} I have seen.",
+        ],
+        Attitude::Condemnation => &[
+            "{topic} is a disgrace and should be stopped.",
+            "What they did with {topic} is shameful.",
+            "There is no excuse for {topic}, it is just wrong.",
+        ],
+        Attitude::Agreement => &[
+            "Exactly, {topic} is just like you said.",
+            "I completely agree about {topic}.",
+            "Yes, this is right, {topic} works that way.",
+        ],
+        Attitude::Complaint => &[
+            "I am tired of {topic} always being broken.",
+            "Why does {topic} have to be so expensive now?",
+            "{topic} keeps getting worse every year.",
+        ],
+        Attitude::Mocking => &[
+            "Oh sure, {topic} will totally fix everything, lol.",
+            "Wow, {topic}, what a genius idea.",
+            "Imagine thinking {topic} matters, lmao.",
+        ],
+        Attitude::Disagreement => &[
+            "No, that is not how {topic} works at all.",
+            "I disagree, {topic} is nothing like that.",
+            "That is wrong, {topic} has the opposite effect.",
+        ],
+        Attitude::Annoyed => &[
+            "Ugh, not {topic} again.",
+            "Can we please stop posting about {topic}?",
+            "I am so sick of hearing about {topic}.",
+        ],
+        Attitude::Neutral | Attitude::Custom(_) => &[
+            "{topic} was mentioned in the news today.",
+            "There is a thread about {topic} from last week.",
+            "Here is a link about {topic}.",
+        ],
+    }
+}
+
+/// Topics that belong to each subject.
+fn topics(subject: Subject) -> &'static [&'static str] {
+    match subject {
+        Subject::Politics => &["the election", "the new tax bill", "the senate vote", "the prime minister"],
+        Subject::Religion => &["the church", "prayer", "the pope's speech", "religious holidays"],
+        Subject::Science => &["the new telescope images", "climate research", "quantum physics", "the vaccine trial"],
+        Subject::Food => &["homemade pizza", "sourdough bread", "this ramen recipe", "spicy food"],
+        Subject::Animals => &["my cat", "this puppy", "wild foxes", "the zoo's new elephant"],
+        Subject::Sports => &["the football match", "the playoffs", "the new coach", "the marathon"],
+        Subject::Music => &["the new album", "the concert last night", "learning guitar", "this band"],
+        Subject::Movies => &["the new trailer", "the sequel", "the director's cut", "the movie's ending"],
+        Subject::Joke => &["a horse walking into a bar", "the knock knock joke", "dad jokes", "this pun"],
+        Subject::Technology => &["the new phone", "Rust's borrow checker", "the software update", "self-driving cars"],
+        Subject::Discussion => &["this debate", "the community rules", "the best approach", "this question"],
+        Subject::Personal => &["my job interview", "my first apartment", "my family", "my birthday"],
+        Subject::Other | Subject::Custom(_) => &["this thing", "the weather", "that old building", "the weekend"],
+    }
+}
+
+/// A small, fast pseudo random number generator (SplitMix64).
+/// It is implemented here so the same seed generates the same subreddit on every platform and version.
+pub(super) struct Rng(u64);
+
+impl Rng {
+    pub(super) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in [0, 1).
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A number in [0, n).
+    pub(super) fn below(&mut self, n: usize) -> usize {
+        (self.uniform() * n as f64) as usize
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        self.uniform() < probability
+    }
+
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+
+    /// A number from the standard normal distribution, with the Box-Muller transform.
+    fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.uniform();
+        let v = self.uniform();
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+
+    /// A count from the Poisson distribution with the given mean, with Knuth's algorithm.
+    fn poisson(&mut self, mean: f64) -> usize {
+        if mean <= 0.0 {
+            return 0;
+        }
+        let limit = (-mean).exp();
+        let mut count = 0;
+        let mut product = self.uniform();
+        while product > limit {
+            count += 1;
+            product *= self.uniform();
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_comments(comments: &[Comment]) -> usize {
+        comments.iter().map(|comment| 1 + count_comments(&comment.comments)).sum()
+    }
+
+    #[test]
+    fn the_same_seed_generates_the_same_subreddit() {
+        let synthetic = Synthetic { seed: 7, posts: 20, ..Default::default() };
+        let (subreddit, labels) = synthetic.generate_labeled();
+        let (again, labels_again) = synthetic.generate_labeled();
+        assert_eq!(serde_json::to_string(&subreddit).unwrap(), serde_json::to_string(&again).unwrap());
+        assert_eq!(serde_json::to_string(&labels).unwrap(), serde_json::to_string(&labels_again).unwrap());
+
+        let other = Synthetic { seed: 8, ..synthetic }.generate();
+        assert_ne!(serde_json::to_string(&subreddit).unwrap(), serde_json::to_string(&other).unwrap());
+    }
+
+    #[test]
+    fn every_post_and_comment_is_labeled_once() {
+        let (subreddit, labels) = Synthetic { seed: 3, posts: 20, ..Default::default() }.generate_labeled();
+        assert_eq!(subreddit.posts.len(), 20);
+        let submissions = subreddit.posts.iter().map(|post| 1 + count_comments(&post.comments)).sum::<usize>();
+        assert_eq!(labels.len(), submissions);
+        let mut ids = labels.iter().map(|label| label.id.as_str()).collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids.len(), submissions);
+    }
+}
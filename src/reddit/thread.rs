@@ -0,0 +1,84 @@
+use super::{crosspost_origin, split_failed, Client, Post, Progress, Scrape, ScrapeError, Scraper, Subreddit};
+use std::{fs::read_to_string, path::Path};
+
+/// The most comments Reddit returns for a single thread.
+const COMMENTS_PER_THREAD: u32 = 500;
+
+/// Get the base36 ID of a post from a URL, permalink, short link or ID, such as
+/// `https://www.reddit.com/r/rust/comments/yv7kdi/title/`, `/r/rust/comments/yv7kdi/`,
+/// `https://redd.it/yv7kdi`, `t3_yv7kdi` or `yv7kdi`.
+pub fn parse_post_id(thread: &str) -> Option<String> {
+    let thread = thread.trim();
+    let segments = thread.split(['/', '?', '#']).filter(|segment| !segment.is_empty()).collect::<Vec<_>>();
+    let id = if let Some(i) = segments.iter().position(|segment| *segment == "comments") {
+        segments.get(i + 1)?
+    } else if let Some(i) = segments.iter().position(|segment| *segment == "redd.it") {
+        segments.get(i + 1)?
+    } else if segments.len() == 1 {
+        segments[0].trim_start_matches("t3_")
+    } else {
+        return None
+    };
+
+    let is_base36 = !id.is_empty() && id.chars().all(|c| c.is_ascii_digit() || c.is_ascii_lowercase());
+    is_base36.then(|| id.to_string())
+}
+
+/// Read thread URLs, permalinks or IDs from a file, one per line.
+/// Blank lines and lines starting with `#` are skipped.
+pub fn read_threads(file: &Path) -> Result<Vec<String>, ScrapeError> {
+    let input = read_to_string(file).map_err(|_| ScrapeError::CouldNotRead(file.display().to_string()))?;
+    Ok(input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Collect a single thread, given its URL, permalink or ID.
+/// The thread itself is never filtered out, but its comments are.
+impl Scrape<&str> for Post {
+    fn scrape(thread: &str) -> Result<Box<Self>, ScrapeError> {
+        let id = parse_post_id(thread)
+            .ok_or_else(|| ScrapeError::InvalidInput(format!("\"{thread}\" is not a thread URL, permalink or ID")))?;
+        let (raw_post, raw_comments) = Client::shared().thread::<serde_json::Value>(&id, Some(COMMENTS_PER_THREAD))?;
+        let crosspost_from = crosspost_origin(&raw_post);
+        let post = Self::scrape((serde_json::from_value::<roux::submission::SubmissionData>(raw_post)?, raw_comments))?;
+        Ok(Box::new(Self { crosspost_from, ..*post }))
+    }
+}
+
+impl Scraper {
+    /// Collect threads, given their URLs, permalinks or IDs, into a dataset named `name`, and store it in the sink.
+    /// The threads themselves are never filtered out, but their comments are. Each thread is collected with
+    /// the most comments Reddit returns for it, instead of the config's comments per post.
+    /// A dataset the sink already has is only collected again if the config doesn't skip existing ones.
+    pub fn threads<T>(&self, name: &str, threads: &[T]) -> Result<Subreddit, ScrapeError> where T: AsRef<str> + Sync {
+        self.check_new(name, "threads")?;
+        self.reporting_waits(|| self.collect_into_sink(0, name, || {
+            self.report(Progress::PostsListed { worker: 0, subreddit: name.to_string(), posts: threads.len() });
+            let filters = &self.config().filters;
+            let results = self.on_pool(threads.iter().enumerate().collect(), |(index, thread)| {
+                let thread = thread.as_ref();
+                let id = parse_post_id(thread);
+                self.fetch_post(0, name, index + 1, (id.as_deref().unwrap_or(thread), ""), || {
+                    let id = id.as_deref().ok_or_else(|| ScrapeError::InvalidInput(format!("\"{thread}\" is not a thread URL, permalink or ID")))?;
+                    // The raw listing is read, since `roux` leaves out where crossposts came from.
+                    let (raw_post, raw_comments) = self.client().thread::<serde_json::Value>(id, Some(COMMENTS_PER_THREAD))?;
+                    let crosspost_from = crosspost_origin(&raw_post);
+                    Ok(Post { crosspost_from, ..Post::from_listing(serde_json::from_value(raw_post)?, raw_comments, filters) })
+                })
+            });
+            let (posts, failed_posts) = split_failed(results);
+            Ok(Subreddit {
+                name: name.to_string(),
+                description: format!("Ad-hoc collection of {} threads", threads.len()),
+                subscribers: 0,
+                posts,
+                failed_posts,
+                filtered: vec![],
+            })
+        }))
+    }
+}
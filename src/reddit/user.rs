@@ -0,0 +1,97 @@
+use crate::events::{self, Event};
+use super::{Comment, Data, Filtered, Post, ScrapeError, Scraper};
+use serde::{Deserialize, Serialize};
+use std::{fs::{read_to_string, write}, path::Path, time::Instant};
+
+/// The most posts, and the most comments, collected for a single user.
+const ITEMS_PER_USER: u32 = 500;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct User {
+    /// The user's name, such as "spez".
+    pub name: String,
+    /// The posts the user has submitted, newest first.
+    /// Each post records the subreddit it was submitted to.
+    pub posts: Vec<Post>,
+    /// The comments the user has posted, newest first.
+    /// Each comment records the subreddit it was posted in.
+    pub comments: Vec<Comment>,
+    /// The posts and comments that were left out by the collection filters, and why.
+    #[serde(default)]
+    pub filtered: Vec<Filtered>,
+}
+
+impl Data for User {
+    fn save(&self, file: &Path) {
+        if let Ok(output_json) = serde_json::to_string(&self) {
+            write(file, output_json).unwrap();
+        }
+    }
+
+    fn restore(&mut self, file: &Path) {
+        if let Ok(input_json) = read_to_string(file) {
+            *self = serde_json::from_str(&input_json).unwrap();
+        }
+    }
+}
+
+impl Scraper {
+    /// Collect a user's submitted posts and comments, and store them in the sink.
+    /// Replies to the user's posts and comments are not collected.
+    /// Posts and comments excluded by the filters are left out, and recorded on the user.
+    pub fn user(&self, name: &str) -> Result<User, ScrapeError> {
+        let client = self.client();
+        let filters = &self.config().filters;
+        let mut filtered = vec![];
+        let started = Instant::now();
+        let posts: Vec<Post> = client.user_submitted(name, ITEMS_PER_USER)?
+            .into_iter()
+            .filter(|post| match filters.post_reason(post) {
+                Some(reason) => {
+                    filtered.push(Filtered { id: post.id.clone(), reason });
+                    false
+                }
+                None => true,
+            })
+            .map(|post| Post {
+                id: post.id,
+                created_utc: post.created_utc,
+                subreddit: post.subreddit,
+                title: post.title,
+                not_safe_for_work: post.over_18,
+                locked: post.locked,
+                body: post.selftext,
+                score: post.score as i32,
+                comments: vec![],
+                filtered: vec![],
+                crosspost_from: None,
+            })
+            .collect();
+        // The posts are listed together, so each is reported with the time the listing took.
+        let elapsed_ms = started.elapsed().as_millis();
+        for post in &posts {
+            events::emit(Event::PostFetched { subreddit: format!("u/{name}"), id: post.id.clone(), comments: 0, elapsed_ms });
+        }
+        let comments = client.user_comments(name, ITEMS_PER_USER)?
+            .into_iter()
+            .filter(|comment| match filters.comment_reason(comment) {
+                Some(reason) => {
+                    filtered.push(Filtered { id: comment.id.clone().unwrap_or_default(), reason });
+                    false
+                }
+                None => true,
+            })
+            .map(|comment| Comment {
+                id: comment.id.unwrap_or_default(),
+                subreddit: comment.subreddit.unwrap_or_default(),
+                body: comment.body.unwrap_or_default(),
+                score: comment.score.unwrap_or(0),
+                comments: vec![],
+            })
+            .collect();
+
+        let user = User { name: name.to_string(), posts, comments, filtered };
+        self.sink().put_user(&user)?;
+        Ok(user)
+    }
+}
@@ -0,0 +1,174 @@
+use super::{canonical_name, crosspost_origin, Comment, FailedPost, FilterReason, Filtered, Listed, Post, ScrapeError, Scraper, Subreddit};
+use roux::submission::SubmissionData;
+use std::{thread::sleep, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+/// Polls the newest posts of some subreddits, and appends unseen posts and comments to their datasets.
+#[derive(Clone, Debug)]
+pub struct Watch {
+    /// The subreddits to watch.
+    pub subreddits: Vec<String>,
+    /// How long to wait between polls.
+    pub interval: Duration,
+    /// Posts younger than this are revisited on every poll for new replies.
+    pub revisit_age: Duration,
+    /// How many of the newest posts to list on every poll.
+    pub posts_per_poll: u32,
+}
+
+/// What a single poll of a subreddit found.
+#[derive(Clone, Debug, Default)]
+pub struct WatchUpdate {
+    /// The subreddit that was polled.
+    pub subreddit: String,
+    /// Posts that had not been seen before, with their comments.
+    pub new_posts: Vec<Post>,
+    /// Comments that had not been seen before, on posts that had. Each comment includes its replies.
+    pub new_comments: Vec<Comment>,
+    /// Posts whose comments could not be retrieved. New posts among them are tried again on the next poll.
+    pub failed_posts: Vec<FailedPost>,
+}
+
+impl WatchUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.new_posts.is_empty() && self.new_comments.is_empty()
+    }
+}
+
+impl Watch {
+    /// Poll every subreddit forever with the scraper, storing each dataset in the scraper's sink after it is polled.
+    /// The datasets the sink already has are added to. Posts and comments are collected with the scraper's config.
+    /// `on_update` is called with what every poll found, and with the errors of failed polls.
+    pub fn run(&self, scraper: &Scraper, mut on_update: impl FnMut(Result<&WatchUpdate, (&str, ScrapeError)>)) -> ! {
+        loop {
+            for name in self.subreddits.iter().map(|name| canonical_name(name)) {
+                let mut subreddit = scraper.sink().get(&name).unwrap_or_else(|| {
+                    let about = scraper.client().about(&name).ok();
+                    Subreddit {
+                        description: about.as_ref().and_then(|about| about.public_description.clone()).unwrap_or_default(),
+                        subscribers: about.and_then(|about| about.subscribers).unwrap_or(0),
+                        name: name.clone(),
+                        ..Default::default()
+                    }
+                });
+
+                match self.poll(scraper, &mut subreddit) {
+                    Ok(update) if update.is_empty() => on_update(Ok(&update)),
+                    Ok(update) => match scraper.sink().put(&subreddit) {
+                        Ok(()) => on_update(Ok(&update)),
+                        Err(err) => on_update(Err((&name, err))),
+                    },
+                    Err(err) => on_update(Err((&name, err))),
+                }
+            }
+            sleep(self.interval);
+        }
+    }
+
+    /// Poll a subreddit once, adding what is new to it.
+    /// Posts whose comments can't be retrieved are reported in the update, without failing the poll.
+    pub fn poll(&self, scraper: &Scraper, subreddit: &mut Subreddit) -> Result<WatchUpdate, ScrapeError> {
+        let mut update = WatchUpdate { subreddit: subreddit.name.clone(), ..WatchUpdate::default() };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        let (client, config) = (scraper.client(), scraper.config());
+        let filters = &config.filters;
+
+        // Revisit the recent posts we already have for new replies.
+        let recent = subreddit.posts
+            .iter()
+            .enumerate()
+            .filter(|(_, post)| !post.id.is_empty() && now - post.created_utc <= self.revisit_age.as_secs_f64())
+            .map(|(i, post)| (i, post.id.clone()))
+            .collect::<Vec<_>>();
+        let name = &subreddit.name;
+        let revisited = scraper.on_pool(recent, |(i, id)| (i, client.article_comments(name, &id, None, Some(config.comments_per_post))));
+        for (i, raw_comments) in revisited {
+            let post = &mut subreddit.posts[i];
+            let raw_comments = match raw_comments {
+                Ok(raw_comments) => raw_comments,
+                Err(err) => {
+                    update.failed_posts.push(FailedPost { id: post.id.clone(), title: post.title.clone(), error: err.to_string() });
+                    continue;
+                }
+            };
+            let mut filtered = vec![];
+            let comments = filters.comments(raw_comments.data.children, &mut filtered);
+            for comment in filtered {
+                if !post.filtered.iter().any(|known| known.id == comment.id) {
+                    post.filtered.push(comment);
+                }
+            }
+            merge_comments(&mut post.comments, comments, &mut update.new_comments);
+        }
+
+        let mut listed = vec![];
+        // The raw listing is read, since `roux` leaves out where crossposts came from.
+        for (index, raw_post) in client.new_posts::<serde_json::Value>(&subreddit.name, self.posts_per_poll)?.into_iter().enumerate() {
+            let crosspost_from = crosspost_origin(&raw_post);
+            let raw_post: SubmissionData = serde_json::from_value(raw_post)?;
+            if let Some(known) = subreddit.posts.iter_mut().find(|post| is_listed(post, &raw_post)) {
+                // Posts collected before IDs were recorded get them now, so they can be revisited.
+                if known.id.is_empty() {
+                    known.id = raw_post.id.clone();
+                    known.created_utc = raw_post.created_utc;
+                    known.subreddit = raw_post.subreddit.clone();
+                }
+                continue;
+            }
+            // New posts always start with a low score and few comments, so posts left out for those
+            // are checked again on every poll, and collected once they pass.
+            let known_filtered = subreddit.filtered.iter().position(|filtered| filtered.id == raw_post.id);
+            if let Some(i) = known_filtered {
+                if !matches!(subreddit.filtered[i].reason, FilterReason::LowScore(_) | FilterReason::FewComments(_)) {
+                    continue;
+                }
+            }
+            match (filters.post_reason(&raw_post), known_filtered) {
+                (Some(reason), Some(i)) => {
+                    subreddit.filtered[i].reason = reason;
+                    continue;
+                }
+                (Some(reason), None) => {
+                    subreddit.filtered.push(Filtered { id: raw_post.id, reason });
+                    continue;
+                }
+                (None, Some(i)) => {
+                    subreddit.filtered.remove(i);
+                }
+                (None, None) => {}
+            }
+            listed.push(Listed { index: index + 1, crosspost_from, post: raw_post });
+        }
+
+        let (posts, failed_posts) = scraper.fetch_comments(0, &subreddit.name, listed);
+        update.new_posts.extend(posts.iter().cloned());
+        subreddit.posts.extend(posts);
+        update.failed_posts.extend(failed_posts);
+        Ok(update)
+    }
+}
+
+/// Whether a post we have is a listed post. Posts collected before IDs were recorded are matched by their text.
+fn is_listed(known: &Post, listed: &SubmissionData) -> bool {
+    if known.id.is_empty() {
+        known.title == listed.title && known.body == listed.selftext
+    } else {
+        known.id == listed.id
+    }
+}
+
+/// Merge freshly retrieved comments into the comments we already have, by ID.
+/// Scores of known comments are updated, and unseen comments are added and also pushed to `new`.
+fn merge_comments(existing: &mut Vec<Comment>, retrieved: Vec<Comment>, new: &mut Vec<Comment>) {
+    for comment in retrieved {
+        match existing.iter_mut().find(|known| !known.id.is_empty() && known.id == comment.id) {
+            Some(known) => {
+                known.score = comment.score;
+                merge_comments(&mut known.comments, comment.comments, new);
+            }
+            None => {
+                new.push(comment.clone());
+                existing.push(comment);
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Authentication tests against a local stand-in for Reddit's token endpoint and API.
+
+use reddit_analyzer::*;
+use std::{io::{BufRead, BufReader, Read, Write}, net::TcpListener, sync::{Arc, Mutex}, thread};
+
+/// A request the mock server received: its request line, headers in lower case, and body.
+#[derive(Clone, Debug)]
+struct Request {
+    line: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// Serve `respond`'s status and JSON body for every request, and record the requests.
+fn serve(respond: impl Fn(&Request, usize) -> (u16, String) + Send + 'static) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(vec![]));
+    let recorded = requests.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut headers = vec![];
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                match header.trim_end().split_once(": ") {
+                    Some((key, value)) => headers.push((key.to_lowercase(), value.to_string())),
+                    None => break,
+                }
+            }
+            let length = headers.iter().find(|(key, _)| key == "content-length").map_or(0, |(_, value)| value.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let request = Request { line: line.trim_end().to_string(), headers, body: String::from_utf8(body).unwrap() };
+
+            let mut requests = recorded.lock().unwrap();
+            let (status, body) = respond(&request, requests.len());
+            requests.push(request);
+            drop(requests);
+            write!(
+                stream,
+                "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len(),
+            )
+            .unwrap();
+        }
+    });
+    (url, requests)
+}
+
+fn credentials(server: &str) -> Credentials {
+    Credentials {
+        client_id: "id".to_string(),
+        client_secret: "secret".to_string(),
+        user_agent: "reddit-analyzer-tests".to_string(),
+        username: None,
+        password: None,
+        token_url: Some(format!("{server}/api/v1/access_token")),
+    }
+}
+
+const ABOUT: &str = r#"{"kind": "t5", "data": {"public_description": "Rust", "subscribers": 300000}}"#;
+
+#[test]
+fn requests_a_token_once_and_sends_it_with_every_request() {
+    let (server, requests) = serve(|request, _| match request.line.as_str() {
+        "POST /api/v1/access_token HTTP/1.1" => (200, r#"{"access_token": "mock-token", "expires_in": 3600}"#.to_string()),
+        _ => (200, ABOUT.to_string()),
+    });
+    let client = Client::authenticated(credentials(&server)).with_base_url(&server);
+    assert_eq!(client.about("rust").unwrap().subscribers, Some(300000));
+    assert_eq!(client.about("rust").unwrap().subscribers, Some(300000));
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    let token_request = &requests[0];
+    assert_eq!(token_request.line, "POST /api/v1/access_token HTTP/1.1");
+    // "id:secret" in base64.
+    assert_eq!(token_request.header("authorization"), Some("Basic aWQ6c2VjcmV0"));
+    assert_eq!(token_request.body, "grant_type=client_credentials");
+    for request in &requests[1..] {
+        assert!(request.line.starts_with("GET /r/rust/about.json?"));
+        assert_eq!(request.header("authorization"), Some("Bearer mock-token"));
+        assert_eq!(request.header("user-agent"), Some("reddit-analyzer-tests"));
+    }
+}
+
+#[test]
+fn requests_a_new_token_after_the_api_rejects_one() {
+    let (server, requests) = serve(|request, index| match (request.line.as_str(), index) {
+        ("POST /api/v1/access_token HTTP/1.1", 0) => (200, r#"{"access_token": "expired", "expires_in": 3600}"#.to_string()),
+        ("POST /api/v1/access_token HTTP/1.1", _) => (200, r#"{"access_token": "fresh", "expires_in": 3600}"#.to_string()),
+        _ if request.header("authorization") == Some("Bearer expired") => (401, "{}".to_string()),
+        _ => (200, ABOUT.to_string()),
+    });
+    let client = Client::authenticated(credentials(&server)).with_base_url(&server);
+    assert!(client.about("rust").is_ok());
+    let tokens = requests.lock().unwrap().iter().filter(|request| request.line.starts_with("POST")).count();
+    assert_eq!(tokens, 2);
+}
+
+#[test]
+fn reports_rejected_credentials() {
+    let (server, _) = serve(|_, _| (200, r#"{"error": "invalid_grant"}"#.to_string()));
+    let client = Client::authenticated(credentials(&server)).with_base_url(&server);
+    match client.about("rust") {
+        Err(ScrapeError::Auth(error)) => assert_eq!(error, "invalid_grant"),
+        other => panic!("expected an authentication error, got {:?}", other.map(|about| about.subscribers)),
+    }
+}
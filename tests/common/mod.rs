@@ -0,0 +1,9 @@
+//! Helpers shared by the integration tests.
+
+use reddit_analyzer::{Client, Fixtures};
+use std::path::PathBuf;
+
+/// A client that replays the API responses checked in to `tests/fixtures`, so tests run offline.
+pub fn replay() -> Client {
+    Client::default().with_fixtures(Fixtures::Replay(PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))))
+}
@@ -0,0 +1,108 @@
+//! Collection tests that replay the API responses checked in to `tests/fixtures`, so they run offline.
+
+mod common;
+
+use common::replay;
+use reddit_analyzer::*;
+
+fn scraper(filters: Filters) -> Scraper {
+    Scraper::builder()
+        .client(replay())
+        .config(ScraperConfig { workers: 1, filters, ..Default::default() })
+        .sink(Discard)
+        .build()
+}
+
+#[test]
+fn collects_a_subreddit() {
+    let (subreddits, report) = scraper(Filters::default()).run(&["rust"]);
+    let rust = &subreddits[0];
+    assert_eq!(rust.name, "rust");
+    assert_eq!(rust.subscribers, 300000);
+    assert_eq!(rust.description, "A place for all things related to the Rust programming language.");
+    assert_eq!(rust.posts.iter().map(|post| post.id.as_str()).collect::<Vec<_>>(), ["abc123", "def456"]);
+
+    let post = &rust.posts[0];
+    assert_eq!(post.title, "Is the borrow checker worth it?");
+    assert_eq!(post.body, "I keep fighting it, but my code has fewer bugs.");
+    assert_eq!(post.score, 42);
+    assert_eq!(post.comments.len(), 2);
+    assert_eq!(post.comments[0].comments[0].body, "Agreed, once it clicks it helps.");
+    assert!(rust.posts[1].comments.is_empty());
+
+    // The post without a recorded response is reported, and does not fail the subreddit.
+    assert_eq!(rust.failed_posts.len(), 1);
+    assert_eq!(rust.failed_posts[0].id, "ghi789");
+    assert_eq!(report.collected, [("rust".to_string(), 2)]);
+    assert!(!report.is_complete());
+}
+
+#[test]
+fn filters_replayed_posts_and_comments() {
+    let filters = Filters { exclude_stickied: true, exclude_removed: true, ..Default::default() };
+    let (subreddits, _) = scraper(filters).run(&["rust"]);
+    let rust = &subreddits[0];
+    assert_eq!(rust.filtered.len(), 1);
+    assert_eq!(rust.filtered[0].id, "def456");
+    assert_eq!(rust.filtered[0].reason, FilterReason::Stickied);
+
+    let post = &rust.posts[0];
+    assert_eq!(post.comments.len(), 1);
+    assert_eq!(post.filtered.len(), 1);
+    assert_eq!(post.filtered[0].id, "c3");
+    assert_eq!(post.filtered[0].reason, FilterReason::Removed);
+}
+
+#[test]
+fn subreddits_are_collected_under_their_canonical_name() {
+    let (subreddits, report) = scraper(Filters::default()).run(&["Rust"]);
+    assert_eq!(subreddits[0].name, "rust");
+    assert_eq!(report.collected, [("rust".to_string(), 2)]);
+}
+
+#[test]
+fn a_subreddit_without_fixtures_fails() {
+    let (subreddits, report) = scraper(Filters::default()).run(&["missing"]);
+    assert!(subreddits.is_empty());
+    assert_eq!(report.failed_subreddits[0].name, "missing");
+}
+
+#[test]
+fn scrapes_threads_and_listed_posts_with_the_shared_client() {
+    Client::set_shared(replay());
+
+    let thread = Post::scrape("https://www.reddit.com/r/rust/comments/abc123/is_the_borrow_checker_worth_it/").unwrap();
+    assert_eq!(thread.id, "abc123");
+    assert_eq!(thread.comments.len(), 2);
+
+    let listed = Client::shared().hot("rust", 100).unwrap().data.children.remove(0).data;
+    let post = Post::scrape(listed).unwrap();
+    assert_eq!(post.id, "abc123");
+    assert_eq!(post.comments[0].comments.len(), 1);
+
+    assert!(matches!(Post::scrape("zzz999").map(|_| ()), Err(ScrapeError::CouldNotRead(_))));
+    assert!(matches!(Post::scrape("https://example.com/not/a/thread").map(|_| ()), Err(ScrapeError::InvalidInput(_))));
+}
+
+#[test]
+fn search_results_are_not_collected_over_an_existing_dataset() {
+    struct Existing;
+
+    impl Sink for Existing {
+        fn contains(&self, subreddit: &str) -> bool {
+            subreddit == "borrow"
+        }
+
+        fn put(&self, subreddit: &Subreddit) -> Result<(), ScrapeError> {
+            panic!("{} was stored over an existing dataset", subreddit.name)
+        }
+
+        fn put_user(&self, user: &User) -> Result<(), ScrapeError> {
+            panic!("u/{} was stored", user.name)
+        }
+    }
+
+    let scraper = Scraper::builder().client(replay()).sink(Existing).build();
+    let search = Search { query: "borrow checker".to_string(), subreddits: vec![], time: TimeRange::All, limit: 10 };
+    assert!(matches!(scraper.search("borrow", &search).map(|_| ()), Err(ScrapeError::InvalidInput(_))));
+}
@@ -0,0 +1,29 @@
+//! Runs a generated subreddit through the analysis with the keyword lexicon, so the pipeline is exercised offline.
+
+use reddit_analyzer::*;
+use std::collections::BTreeMap;
+
+#[test]
+fn analyzes_a_generated_subreddit_with_the_lexicon() {
+    set_classifier(Backend::Lexicon.classifier(None).unwrap());
+    set_post_content(PostContent::Body);
+    let (subreddit, labels) = Synthetic { seed: 11, posts: 10, ..Default::default() }.generate_labeled();
+    let written = labels.iter().map(|label| (label.id.as_str(), label.attitude)).collect::<BTreeMap<_, _>>();
+
+    let (mut bodies, mut matched) = (0, 0);
+    for post in &subreddit.posts {
+        let analysis = post.analyze_submission().unwrap();
+        // Only the body is analyzed on its own, unless there is no body and the title is all there is.
+        assert_eq!(analysis.title.is_some(), post.body.is_empty());
+        assert_eq!(analysis.body.is_some(), !post.body.is_empty());
+        assert_eq!(analysis.children.len(), post.comments.len());
+        if post.body.is_empty() {
+            continue;
+        }
+        bodies += 1;
+        matched += usize::from(analysis.body.unwrap().attitude == written[post.id.as_str()]);
+    }
+    // The generated sentences use the lexicon's keywords, so most bodies read as the attitude they were written with.
+    assert!(bodies > 0);
+    assert!(matched * 2 > bodies, "{matched} of {bodies} bodies matched");
+}
@@ -0,0 +1,75 @@
+//! Watch mode tests that replay the API responses checked in to `tests/fixtures`.
+
+mod common;
+
+use common::replay;
+use reddit_analyzer::*;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn watch() -> Watch {
+    Watch {
+        subreddits: vec!["rust".to_string()],
+        interval: Duration::from_secs(300),
+        // Long enough that the fixtures' posts are still revisited.
+        revisit_age: Duration::from_secs(20 * 365 * 24 * 3600),
+        posts_per_poll: 25,
+    }
+}
+
+fn scraper(filters: Filters) -> Scraper {
+    Scraper::builder()
+        .client(replay())
+        .config(ScraperConfig { comments_per_post: 100, filters, ..Default::default() })
+        .sink(Discard)
+        .build()
+}
+
+#[test]
+fn failed_posts_are_reported_without_failing_the_poll() {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64();
+    let mut subreddit = Subreddit {
+        name: "rust".to_string(),
+        posts: vec![
+            // Collected before IDs were recorded.
+            Post {
+                title: "Is the borrow checker worth it?".to_string(),
+                body: "I keep fighting it, but my code has fewer bugs.".to_string(),
+                ..Default::default()
+            },
+            Post { id: "gone01".to_string(), created_utc: now, title: "A deleted thread".to_string(), ..Default::default() },
+        ],
+        ..Default::default()
+    };
+
+    let update = watch().poll(&scraper(Filters::default()), &mut subreddit).unwrap();
+    assert!(update.new_posts.is_empty());
+    assert_eq!(update.failed_posts.iter().map(|post| post.id.as_str()).collect::<Vec<_>>(), ["gone01", "ghi789"]);
+    assert_eq!(subreddit.posts.len(), 2);
+    assert_eq!(subreddit.posts[0].id, "abc123");
+
+    // The post that was matched by its text is revisited now that it has an ID, and the failed new post is tried again.
+    let update = watch().poll(&scraper(Filters::default()), &mut subreddit).unwrap();
+    assert_eq!(update.new_comments.len(), 2);
+    assert_eq!(subreddit.posts[0].comments[0].comments.len(), 1);
+    assert_eq!(update.failed_posts.iter().map(|post| post.id.as_str()).collect::<Vec<_>>(), ["gone01", "ghi789"]);
+}
+
+#[test]
+fn posts_left_out_for_their_score_are_checked_again() {
+    let mut subreddit = Subreddit { name: "rust".to_string(), ..Default::default() };
+
+    let strict = scraper(Filters { min_score: Some(100), ..Default::default() });
+    let update = watch().poll(&strict, &mut subreddit).unwrap();
+    assert!(update.new_posts.is_empty());
+    assert_eq!(subreddit.filtered.len(), 2);
+
+    // The posts still score 42, so they stay left out, without being recorded twice.
+    watch().poll(&strict, &mut subreddit).unwrap();
+    assert_eq!(subreddit.filtered.len(), 2);
+
+    let update = watch().poll(&scraper(Filters { min_score: Some(10), ..Default::default() }), &mut subreddit).unwrap();
+    assert_eq!(update.new_posts.iter().map(|post| post.id.as_str()).collect::<Vec<_>>(), ["abc123"]);
+    // The other post passes too, but its comments can't be retrieved, so it is tried again on the next poll.
+    assert_eq!(update.failed_posts[0].id, "ghi789");
+    assert!(subreddit.filtered.is_empty());
+}